@@ -270,6 +270,7 @@ impl RefreshDaemon {
 
         let options = SignerOptions {
             mode: SignerMode::Pem,
+            signature_cache: Some(get_data_path().join("signature_cache")),
             ..Default::default()
         };
 
@@ -296,6 +297,10 @@ impl RefreshDaemon {
             .await
             .map_err(|e| format!("Failed to sign bundle: {}", e))?;
 
+        bundle
+            .ensure_verified()
+            .map_err(|e| format!("Failed to verify signature: {}", e))?;
+
         if !device.is_mac {
             device
                 .install_app(&app.path, |_| async {})
@@ -34,8 +34,8 @@ impl GeneralScreen {
                 return Task::perform(
                     async {
                         rfd::AsyncFileDialog::new()
-                            .add_filter("iOS App Package", &["ipa", "tipa"])
-                            .set_title("Select IPA/TIPA file")
+                            .add_filter("iOS App Package", &["ipa", "tipa", "zip"])
+                            .set_title("Select IPA/TIPA/ZIP file")
                             .pick_file()
                             .await
                             .map(|file| file.path().to_path_buf())
@@ -53,11 +53,9 @@ impl GeneralScreen {
             }
             Message::FilesDropped(paths) => {
                 for path in paths {
-                    if let Some(ext) = path.extension() {
-                        if ext == "ipa" || ext == "tipa" {
-                            if let Ok(package) = Package::new(path) {
-                                return Task::done(Message::NavigateToInstaller(package));
-                            }
+                    if Package::is_supported_path(&path) {
+                        if let Ok(package) = Package::new(path) {
+                            return Task::done(Message::NavigateToInstaller(package));
                         }
                     }
                 }
@@ -1,6 +1,6 @@
 pub(crate) mod general;
 mod package;
-mod progress;
+pub(crate) mod progress;
 pub(crate) mod settings;
 mod utilties;
 mod windows;
@@ -688,11 +688,15 @@ impl Impactor {
         let progress_subscription =
             if let ImpactorScreen::Progress(ref progress) = self.current_screen {
                 subscriptions::installation_progress_listener(progress.progress_rx.clone()).map(
-                    |(status, progress_val)| {
-                        Message::ProgressScreen(progress::Message::InstallationProgress(
-                            status,
-                            progress_val,
-                        ))
+                    |update| {
+                        Message::ProgressScreen(match update {
+                            progress::ProgressUpdate::Status(status, progress_val) => {
+                                progress::Message::InstallationProgress(status, progress_val)
+                            }
+                            progress::ProgressUpdate::Event(event) => {
+                                progress::Message::SigningEvent(event)
+                            }
+                        })
                     },
                 )
             } else {
@@ -833,9 +837,10 @@ impl Impactor {
 
             let (tx, rx) = std::sync::mpsc::channel();
             let progress_rx = std::sync::Arc::new(std::sync::Mutex::new(rx));
+            let cancel = plume_utils::CancellationToken::new();
 
             let mut progress_screen = progress::ProgressScreen::new();
-            progress_screen.start_installation(progress_rx.clone());
+            progress_screen.start_installation(progress_rx.clone(), cancel.clone());
             self.current_screen = ImpactorScreen::Progress(progress_screen);
 
             std::thread::spawn(move || {
@@ -849,6 +854,7 @@ impl Impactor {
                         account.as_ref(),
                         store.as_mut(),
                         &tx,
+                        &cancel,
                     )
                     .await
                     {
@@ -860,7 +866,10 @@ impl Impactor {
                             }
                         }
                         Err(e) => {
-                            let _ = tx_error.send((format!("Error: {}", e), -1));
+                            let _ = tx_error.send(progress::ProgressUpdate::Status(
+                                format!("Error: {}", e),
+                                -1,
+                            ));
 
                             if std::env::var("PLUME_DELETE_AFTER_FINISHED").is_err() {
                                 package.remove_package_stage();
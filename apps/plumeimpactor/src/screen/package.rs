@@ -1,8 +1,13 @@
+use std::fmt;
+
 use iced::widget::{
     button, checkbox, column, container, image, pick_list, row, scrollable, stack, text, text_input,
 };
 use iced::{Alignment, Center, Element, Fill, Task};
-use plume_utils::{Package, PlistInfoTrait, SignerInstallMode, SignerMode, SignerOptions};
+use plume_utils::{
+    ComponentEntitlements, LintReport, LintSeverity, Package, PlistInfoTrait, SignerInstallMode,
+    SignerMode, SignerOptions, UrlSchemeRewrite,
+};
 
 use crate::appearance;
 
@@ -11,6 +16,7 @@ pub enum Message {
     UpdateCustomName(String),
     UpdateCustomIdentifier(String),
     UpdateCustomVersion(String),
+    SelectApp(String),
     ToggleMinimumOsVersion(bool),
     ToggleFileSharing(bool),
     ToggleIpadFullscreen(bool),
@@ -20,6 +26,12 @@ pub enum Message {
     ToggleLiquidGlass(bool),
     ToggleRefresh(bool),
     ToggleElleKit(bool),
+    TogglePackageEntry(String, bool),
+    ToggleComponent(String, bool),
+    ToggleRemoveLocalizations(bool),
+    ToggleRemoveSupportedDevices(bool),
+    ToggleRemoveUrlSchemes(bool),
+    UpdateUrlSchemeSuffix(String),
     UpdateSignerMode(SignerMode),
     UpdateInstallMode(SignerInstallMode),
     AddTweak,
@@ -27,16 +39,38 @@ pub enum Message {
     RemoveTweak(usize),
     SetCustomIcon,
     ClearCustomIcon,
+    AddAlternateIcon,
+    RemoveAlternateIcon(usize),
     SetCustomEntitlements,
     ClearCustomEntitlements,
+    SetComponentEntitlements(EntitlementsComponent),
+    ToggleReplaceComponentEntitlements(String, bool),
+    RemoveComponentEntitlements(String),
+    Lint,
+    LintFinished(Result<LintReport, String>),
     Back,
     RequestInstallation,
 }
 
+/// Key of `SignerOptions::component_entitlements`, `.` being the app itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntitlementsComponent(String);
+
+impl fmt::Display for EntitlementsComponent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.as_str() {
+            "." => write!(f, "App"),
+            component => write!(f, "{component}"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PackageScreen {
     pub selected_package: Option<Package>,
     pub options: SignerOptions,
+    linting: bool,
+    lint_result: Option<Result<LintReport, String>>,
 }
 
 impl PackageScreen {
@@ -44,6 +78,8 @@ impl PackageScreen {
         Self {
             selected_package: package,
             options,
+            linting: false,
+            lint_result: None,
         }
     }
 
@@ -91,6 +127,19 @@ impl PackageScreen {
                 }
                 Task::none()
             }
+            Message::SelectApp(name) => {
+                if let Some(package) = &mut self.selected_package {
+                    if let Some(index) = package.apps().iter().position(|a| a == &name) {
+                        if let Err(e) = package.select_app(index) {
+                            log::error!("Failed to select {name}: {e}");
+                        }
+                    }
+                }
+                self.options.remove_components.clear();
+                self.options.component_entitlements.clear();
+                self.lint_result = None;
+                Task::none()
+            }
             Message::ToggleMinimumOsVersion(value) => {
                 self.options.features.support_minimum_os_version = value;
                 Task::none()
@@ -127,6 +176,43 @@ impl PackageScreen {
                 self.options.features.support_ellekit = value;
                 Task::none()
             }
+            Message::TogglePackageEntry(name, keep) => {
+                self.options.strip_package_entries.retain(|n| n != &name);
+                if !keep {
+                    self.options.strip_package_entries.push(name);
+                }
+                Task::none()
+            }
+            Message::ToggleComponent(name, keep) => {
+                self.options.remove_components.retain(|n| n != &name);
+                if !keep {
+                    self.options.remove_components.push(name);
+                }
+                Task::none()
+            }
+            Message::ToggleRemoveLocalizations(value) => {
+                // keeps whatever languages this computer's user reads
+                self.options.keep_localizations =
+                    value.then(sys_locale::get_locales).map(|l| l.collect());
+                Task::none()
+            }
+            Message::ToggleRemoveSupportedDevices(value) => {
+                self.options.features.remove_supported_devices = value;
+                Task::none()
+            }
+            Message::ToggleRemoveUrlSchemes(value) => {
+                self.options.features.remove_url_schemes = value;
+                // the suffix field is hidden once the schemes are removed
+                if value {
+                    self.options.url_scheme_rewrite = None;
+                }
+                Task::none()
+            }
+            Message::UpdateUrlSchemeSuffix(suffix) => {
+                self.options.url_scheme_rewrite =
+                    (!suffix.is_empty()).then_some(UrlSchemeRewrite::Suffix(suffix));
+                Task::none()
+            }
             Message::UpdateSignerMode(mode) => {
                 self.options.mode = mode;
                 Task::none()
@@ -192,6 +278,31 @@ impl PackageScreen {
                 self.options.custom_icon = None;
                 Task::none()
             }
+            Message::AddAlternateIcon => {
+                let path = rfd::FileDialog::new()
+                    .add_filter("Image files", &["png", "jpg", "jpeg"])
+                    .set_title("Select Alternate Icon")
+                    .pick_file();
+
+                // named after the file, that's what the app asks for when switching
+                if let Some(path) = path {
+                    if let Some(name) = path.file_stem().and_then(|n| n.to_str()) {
+                        let name = name.to_string();
+                        self.options
+                            .custom_alternate_icons
+                            .retain(|(n, _)| n != &name);
+                        self.options.custom_alternate_icons.push((name, path));
+                    }
+                }
+
+                Task::none()
+            }
+            Message::RemoveAlternateIcon(index) => {
+                if index < self.options.custom_alternate_icons.len() {
+                    self.options.custom_alternate_icons.remove(index);
+                }
+                Task::none()
+            }
             Message::SetCustomEntitlements => {
                 let path = rfd::FileDialog::new()
                     .add_filter("Entitlements plist", &["plist", "xml"])
@@ -208,6 +319,65 @@ impl PackageScreen {
                 self.options.custom_entitlements = None;
                 Task::none()
             }
+            Message::SetComponentEntitlements(component) => {
+                let path = rfd::FileDialog::new()
+                    .add_filter("Entitlements plist", &["plist", "xml"])
+                    .set_title(format!("Select Entitlements for {component}"))
+                    .pick_file();
+
+                if let Some(path) = path {
+                    self.options.component_entitlements.insert(
+                        component.0,
+                        ComponentEntitlements {
+                            path,
+                            replace: false,
+                        },
+                    );
+                }
+
+                Task::none()
+            }
+            Message::ToggleReplaceComponentEntitlements(component, value) => {
+                if let Some(entitlements) = self.options.component_entitlements.get_mut(&component)
+                {
+                    entitlements.replace = value;
+                }
+                Task::none()
+            }
+            Message::RemoveComponentEntitlements(component) => {
+                self.options.component_entitlements.remove(&component);
+                Task::none()
+            }
+            Message::Lint => {
+                let Some(package) = self.selected_package.clone() else {
+                    return Task::none();
+                };
+
+                self.linting = true;
+                self.lint_result = None;
+
+                Task::perform(
+                    async move {
+                        std::thread::spawn(move || {
+                            // extracted into the stage, which installation recreates as needed
+                            let result = package
+                                .get_package_bundle()
+                                .and_then(|bundle| bundle.lint())
+                                .map_err(|e| e.to_string());
+                            package.remove_package_stage();
+                            result
+                        })
+                        .join()
+                        .unwrap_or_else(|_| Err("Bundle check failed".to_string()))
+                    },
+                    Message::LintFinished,
+                )
+            }
+            Message::LintFinished(result) => {
+                self.linting = false;
+                self.lint_result = Some(result);
+                Task::none()
+            }
             _ => Task::none(),
         }
     }
@@ -220,7 +390,7 @@ impl PackageScreen {
         let content = scrollable(
             row![
                 self.view_package_info_column(pkg),
-                self.view_options_column()
+                self.view_options_column(pkg)
             ]
             .spacing(appearance::THEME_PADDING),
         );
@@ -248,7 +418,23 @@ impl PackageScreen {
         let pkg_id = pkg.get_bundle_identifier().unwrap_or_default();
         let pkg_ver = pkg.get_version().unwrap_or_default();
 
+        let apps = pkg.apps();
+        let app_picker: Element<'_, Message> = if apps.len() > 1 {
+            let selected = apps.get(pkg.selected_app()).cloned();
+            column![
+                text("App:").size(12),
+                pick_list(apps, selected, Message::SelectApp)
+                    .style(appearance::s_pick_list)
+                    .placeholder("Select app"),
+            ]
+            .spacing(8)
+            .into()
+        } else {
+            column![].into()
+        };
+
         column![
+            app_picker,
             row![
                 self.view_custom_icon(),
                 column![
@@ -280,6 +466,8 @@ impl PackageScreen {
             text("Entitlements:").size(12),
             self.view_custom_entitlements(),
             text("Only available if \"Only Register Main Bundle\" is enabled.").size(11),
+            text("Component Entitlements:").size(12),
+            self.view_component_entitlements(pkg),
             text("Tweaks:").size(12),
             self.view_tweaks(),
             row![
@@ -291,14 +479,25 @@ impl PackageScreen {
                     .style(appearance::s_button),
             ]
             .spacing(8),
+            text("Alternate Icons:").size(12),
+            self.view_alternate_icons(),
+            button(appearance::icon_text(
+                appearance::PLUS,
+                "Add Alternate Icon",
+                None
+            ))
+            .on_press(Message::AddAlternateIcon)
+            .style(appearance::s_button),
+            text("Checks:").size(12),
+            self.view_lint(),
         ]
         .spacing(8)
         .width(Fill)
         .into()
     }
 
-    fn view_options_column(&self) -> Element<'_, Message> {
-        column![
+    fn view_options_column(&self, pkg: &Package) -> Element<'_, Message> {
+        let options = column![
             text("General:").size(12),
             checkbox(self.options.features.support_minimum_os_version)
                 .label("Support older versions (7+)")
@@ -328,6 +527,17 @@ impl PackageScreen {
             checkbox(self.options.refresh)
                 .label("Auto Refresh [BETA]")
                 .on_toggle(Message::ToggleRefresh),
+            checkbox(self.options.keep_localizations.is_some())
+                .label("Remove Unused Languages")
+                .on_toggle(Message::ToggleRemoveLocalizations),
+            checkbox(self.options.features.remove_supported_devices)
+                .label("Remove Supported Devices List")
+                .on_toggle(Message::ToggleRemoveSupportedDevices),
+            checkbox(self.options.features.remove_url_schemes)
+                .label("Remove URL Schemes")
+                .on_toggle(Message::ToggleRemoveUrlSchemes),
+            self.view_url_scheme_suffix(),
+            self.view_components(pkg),
             text("Mode:").size(12),
             pick_list(
                 &[SignerInstallMode::Install, SignerInstallMode::Export][..],
@@ -346,8 +556,71 @@ impl PackageScreen {
             .placeholder("Select signing method"),
         ]
         .spacing(8)
-        .width(Fill)
-        .into()
+        .width(Fill);
+
+        if self.options.install_mode == SignerInstallMode::Export {
+            options.push(self.view_package_entries(pkg)).into()
+        } else {
+            options.into()
+        }
+    }
+
+    fn view_components(&self, pkg: &Package) -> Element<'_, Message> {
+        let components = pkg.removable_components();
+        if components.is_empty() {
+            return column![].into();
+        }
+
+        // each one needs its own App ID, free accounts only get 10 a week
+        let mut list = column![text("Extensions:").size(12)].spacing(8);
+
+        for name in components {
+            let keep = !self.options.remove_components.contains(&name);
+            list = list.push(
+                checkbox(keep)
+                    .label(name.clone())
+                    .on_toggle(move |value| Message::ToggleComponent(name.clone(), value)),
+            );
+        }
+
+        list.into()
+    }
+
+    /// Lets a second copy of an app keep working URL schemes of its own.
+    fn view_url_scheme_suffix(&self) -> Element<'_, Message> {
+        if self.options.features.remove_url_schemes {
+            return column![].into();
+        }
+
+        let suffix = match &self.options.url_scheme_rewrite {
+            Some(UrlSchemeRewrite::Suffix(suffix)) => suffix.as_str(),
+            _ => "",
+        };
+
+        text_input("URL scheme suffix (e.g. -2)", suffix)
+            .on_input(Message::UpdateUrlSchemeSuffix)
+            .padding(8)
+            .into()
+    }
+
+    fn view_package_entries(&self, pkg: &Package) -> Element<'_, Message> {
+        let entries = pkg.extra_top_level_entries();
+        if entries.is_empty() {
+            return column![].into();
+        }
+
+        let mut list = column![text("Package Contents:").size(12)].spacing(8);
+
+        for name in entries {
+            let keep = !self.options.strip_package_entries.contains(&name);
+            list = list.push(
+                checkbox(keep)
+                    .label(format!("Keep {name}"))
+                    .on_toggle(move |value| Message::TogglePackageEntry(name.clone(), value)),
+            );
+        }
+
+        list.into()
     }
 
     fn view_buttons(&self, has_device: bool) -> Element<'_, Message> {
@@ -355,6 +628,8 @@ impl PackageScreen {
             SignerInstallMode::Install => (has_device, "Install"),
             SignerInstallMode::Export => (true, "Export"),
         };
+        // both use the package stage
+        let button_enabled = button_enabled && !self.linting;
 
         container(
             row![
@@ -416,6 +691,57 @@ impl PackageScreen {
         .into()
     }
 
+    fn view_component_entitlements(&self, pkg: &Package) -> Element<'_, Message> {
+        let mut components: Vec<&String> = self.options.component_entitlements.keys().collect();
+        components.sort();
+
+        let mut list = column![].spacing(4);
+
+        for component in components {
+            let entitlements = &self.options.component_entitlements[component];
+            let file_name = entitlements
+                .path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("???");
+            let toggled = component.clone();
+
+            list = list.push(
+                row![
+                    text(format!(
+                        "{}: {file_name}",
+                        EntitlementsComponent(component.clone())
+                    ))
+                    .size(12)
+                    .width(Fill),
+                    checkbox(entitlements.replace)
+                        .label("Replace")
+                        .on_toggle(move |value| {
+                            Message::ToggleReplaceComponentEntitlements(toggled.clone(), value)
+                        }),
+                    button(appearance::icon(appearance::MINUS))
+                        .on_press(Message::RemoveComponentEntitlements(component.clone()))
+                        .style(appearance::s_button)
+                        .padding(6),
+                ]
+                .spacing(8)
+                .align_y(Alignment::Center),
+            );
+        }
+
+        let choices: Vec<EntitlementsComponent> = std::iter::once(".".to_string())
+            .chain(pkg.removable_components())
+            .map(EntitlementsComponent)
+            .collect();
+
+        list.push(
+            pick_list(choices, None, Message::SetComponentEntitlements)
+                .style(appearance::s_pick_list)
+                .placeholder("Add entitlements for..."),
+        )
+        .into()
+    }
+
     fn view_custom_icon(&self) -> Element<'_, Message> {
         let has_custom = self.options.custom_icon.is_some();
 
@@ -471,6 +797,75 @@ impl PackageScreen {
             .into()
     }
 
+    fn view_lint(&self) -> Element<'_, Message> {
+        let check_button = button(appearance::icon_text(
+            appearance::WRENCH,
+            if self.linting {
+                "Checking..."
+            } else {
+                "Check Bundle"
+            },
+            None,
+        ))
+        .on_press_maybe((!self.linting).then_some(Message::Lint))
+        .style(appearance::s_button);
+
+        let mut list = column![check_button].spacing(4);
+
+        match &self.lint_result {
+            None => {}
+            Some(Err(e)) => {
+                list = list.push(
+                    text(format!("Check failed: {e}"))
+                        .size(12)
+                        .color(iced::Color::from_rgb(0.9, 0.2, 0.2)),
+                );
+            }
+            Some(Ok(report)) if report.is_empty() => {
+                list = list.push(
+                    text("No problems found")
+                        .size(12)
+                        .color(iced::Color::from_rgb(0.2, 0.8, 0.4)),
+                );
+            }
+            Some(Ok(report)) => {
+                for message in &report.messages {
+                    let color = match message.severity() {
+                        LintSeverity::Error => iced::Color::from_rgb(0.9, 0.2, 0.2),
+                        LintSeverity::Warning => iced::Color::from_rgb(0.95, 0.75, 0.2),
+                    };
+                    list = list.push(text(message.to_string()).size(12).color(color));
+                }
+            }
+        }
+
+        list.into()
+    }
+
+    fn view_alternate_icons(&self) -> Element<'_, Message> {
+        if self.options.custom_alternate_icons.is_empty() {
+            return text("No alternate icons added").size(12).into();
+        }
+
+        let mut icon_list = column![].spacing(4);
+
+        for (i, (name, _)) in self.options.custom_alternate_icons.iter().enumerate() {
+            icon_list = icon_list.push(
+                row![
+                    text(name).size(12).width(Fill),
+                    button(appearance::icon(appearance::MINUS))
+                        .on_press(Message::RemoveAlternateIcon(i))
+                        .style(appearance::s_button)
+                        .padding(6)
+                ]
+                .spacing(8)
+                .align_y(Alignment::Center),
+            );
+        }
+
+        icon_list.into()
+    }
+
     fn view_tweaks(&self) -> Element<'_, Message> {
         let tweaks = self.options.tweaks.as_ref();
 
@@ -1,20 +1,30 @@
 use std::sync::{Arc, Mutex, mpsc};
 
-use iced::Element;
 use iced::Length::Fill;
-use iced::Task;
-use iced::widget::{button, column, container, row, text};
+use iced::widget::{button, column, container, row, scrollable, text};
+use iced::{Center, Element, Task};
+use plume_utils::{CancellationToken, SigningEvent, SigningEventKind};
 
 use crate::appearance;
 
-type ProgressReceiver = Arc<Mutex<mpsc::Receiver<(String, i32)>>>;
+type ProgressReceiver = Arc<Mutex<mpsc::Receiver<ProgressUpdate>>>;
+
+/// Sent from the installation thread to the progress screen.
+#[derive(Debug, Clone)]
+pub enum ProgressUpdate {
+    /// Overall status and percentage, -1 for errors.
+    Status(String, i32),
+    Event(SigningEvent),
+}
 
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub enum Message {
     InstallationProgress(String, i32),
+    SigningEvent(SigningEvent),
     InstallationError(String),
     InstallationFinished,
+    Cancel,
     Back,
 }
 
@@ -24,6 +34,8 @@ pub struct ProgressScreen {
     pub progress: i32,
     pub is_installing: bool,
     pub progress_rx: Option<ProgressReceiver>,
+    pub events: Vec<SigningEvent>,
+    pub cancel: Option<CancellationToken>,
 }
 
 impl ProgressScreen {
@@ -33,14 +45,18 @@ impl ProgressScreen {
             progress: 0,
             is_installing: false,
             progress_rx: None,
+            events: Vec::new(),
+            cancel: None,
         }
     }
 
-    pub fn start_installation(&mut self, rx: ProgressReceiver) {
+    pub fn start_installation(&mut self, rx: ProgressReceiver, cancel: CancellationToken) {
         self.is_installing = true;
         self.progress = 0;
         self.status = "Idle.".to_string();
         self.progress_rx = Some(rx);
+        self.events.clear();
+        self.cancel = Some(cancel);
     }
 
     pub fn update(&mut self, message: Message) -> Task<Message> {
@@ -53,6 +69,11 @@ impl ProgressScreen {
                     self.progress_rx = None;
                     self.is_installing = false;
 
+                    if self.is_cancelled() {
+                        self.status = "Cancelled.".to_string();
+                        return Task::none();
+                    }
+
                     let error_msg = status.clone();
                     std::thread::spawn(move || {
                         rfd::MessageDialog::new()
@@ -70,6 +91,20 @@ impl ProgressScreen {
 
                 Task::none()
             }
+            Message::SigningEvent(event) => {
+                if let SigningEventKind::InstallProgress { .. } = event.kind {
+                    // one line that keeps updating instead of one per percent
+                    if let Some(last) = self.events.last_mut() {
+                        if let SigningEventKind::InstallProgress { .. } = last.kind {
+                            *last = event;
+                            return Task::none();
+                        }
+                    }
+                }
+
+                self.events.push(event);
+                Task::none()
+            }
             Message::InstallationError(error) => {
                 self.progress = -1;
                 self.status = format!("Error: {}", error);
@@ -94,10 +129,22 @@ impl ProgressScreen {
 
                 Task::none()
             }
+            Message::Cancel => {
+                if let Some(cancel) = &self.cancel {
+                    cancel.cancel();
+                    self.status = "Cancelling...".to_string();
+                }
+
+                Task::none()
+            }
             Message::Back => Task::none(),
         }
     }
 
+    fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(|c| c.is_cancelled())
+    }
+
     pub fn view(&self) -> Element<'_, Message> {
         let progress_bar = iced::widget::progress_bar(0.0..=100.0, self.progress as f32);
 
@@ -105,7 +152,7 @@ impl ProgressScreen {
             text("Installing application, this will take a moment. Do not disconnect the device until finished.").size(14),
             text(format!("{}% – {}", self.progress, self.status)).size(14),
             progress_bar,
-            container(text("")).height(Fill),
+            self.view_events(),
         ]
         .spacing(appearance::THEME_PADDING);
 
@@ -116,17 +163,38 @@ impl ProgressScreen {
         .into()
     }
 
-    fn view_buttons(&self) -> Element<'_, Message> {
-        container(row![
-            button(appearance::icon_text(
-                appearance::CHEVRON_BACK,
-                "Back",
-                None
-            ))
-            .on_press_maybe((!self.is_installing).then_some(Message::Back))
+    fn view_events(&self) -> Element<'_, Message> {
+        let lines = self.events.iter().fold(column![].spacing(4), |col, event| {
+            col.push(text(format!("{:>6.1}s  {event}", event.elapsed.as_secs_f64())).size(12))
+        });
+
+        scrollable(lines)
+            .anchor_bottom()
             .width(Fill)
-            .style(appearance::s_button)
-        ])
+            .height(Fill)
+            .into()
+    }
+
+    fn view_buttons(&self) -> Element<'_, Message> {
+        container(
+            row![
+                button(appearance::icon_text(
+                    appearance::CHEVRON_BACK,
+                    "Back",
+                    None
+                ))
+                .on_press_maybe((!self.is_installing).then_some(Message::Back))
+                .width(Fill)
+                .style(appearance::s_button),
+                button(text("Cancel").align_x(Center))
+                    .on_press_maybe(
+                        (self.is_installing && !self.is_cancelled()).then_some(Message::Cancel)
+                    )
+                    .width(Fill)
+                    .style(appearance::s_button),
+            ]
+            .spacing(appearance::THEME_PADDING),
+        )
         .width(Fill)
         .into()
     }
@@ -5,7 +5,7 @@ use tray_icon::{TrayIconEvent, menu::MenuEvent};
 
 use crate::{
     defaults::get_data_path,
-    screen::{Message, general},
+    screen::{Message, general, progress::ProgressUpdate},
 };
 use plume_utils::{Bundle, Device, PlistInfoTrait};
 
@@ -161,12 +161,12 @@ pub(crate) fn file_hover_subscription() -> Subscription<Message> {
 }
 
 pub(crate) fn installation_progress_listener(
-    progress_rx: Option<Arc<std::sync::Mutex<std::sync::mpsc::Receiver<(String, i32)>>>>,
-) -> Subscription<(String, i32)> {
+    progress_rx: Option<Arc<std::sync::Mutex<std::sync::mpsc::Receiver<ProgressUpdate>>>>,
+) -> Subscription<ProgressUpdate> {
     match progress_rx {
         Some(rx) => {
             struct State {
-                rx: Arc<std::sync::Mutex<std::sync::mpsc::Receiver<(String, i32)>>>,
+                rx: Arc<std::sync::Mutex<std::sync::mpsc::Receiver<ProgressUpdate>>>,
             }
 
             impl std::hash::Hash for State {
@@ -180,11 +180,11 @@ pub(crate) fn installation_progress_listener(
                 let rx = state.rx.clone();
                 iced::stream::channel(
                     100,
-                    move |mut output: iced::futures::channel::mpsc::Sender<(String, i32)>| async move {
+                    move |mut output: iced::futures::channel::mpsc::Sender<ProgressUpdate>| async move {
                         use iced::futures::{SinkExt, StreamExt};
 
                         let (tx, mut rx_stream) =
-                            iced::futures::channel::mpsc::unbounded::<(String, i32)>();
+                            iced::futures::channel::mpsc::unbounded::<ProgressUpdate>();
 
                         let rx_thread = rx.clone();
                         std::thread::spawn(move || {
@@ -197,8 +197,8 @@ pub(crate) fn installation_progress_listener(
                                     }
                                 };
 
-                                if let Some((status, progress)) = message {
-                                    let _ = tx.unbounded_send((status, progress));
+                                if let Some(update) = message {
+                                    let _ = tx.unbounded_send(update);
                                 }
 
                                 std::thread::sleep(std::time::Duration::from_millis(100));
@@ -222,18 +222,45 @@ pub(crate) async fn run_installation(
     options: &plume_utils::SignerOptions,
     account: Option<&plume_store::GsaAccount>,
     mut store: Option<&mut plume_store::AccountStore>,
-    tx: &std::sync::mpsc::Sender<(String, i32)>,
+    tx: &std::sync::mpsc::Sender<ProgressUpdate>,
+    cancel: &plume_utils::CancellationToken,
 ) -> Result<(), String> {
     use plume_core::{AnisetteConfiguration, CertificateIdentity, developer::DeveloperSession};
-    use plume_utils::{Signer, SignerInstallMode, SignerMode};
+    use plume_utils::{
+        Signer, SignerInstallMode, SignerMode, SigningEvent, SigningEventKind, SigningEvents,
+    };
 
     let package_file: Bundle;
     let mut options = options.clone();
+    // what's needed to delete the App IDs registered for this run if it's cancelled
+    let mut registered: Option<(Signer, DeveloperSession, String)> = None;
     let send = |msg: String, progress: i32| {
-        let _ = tx.send((msg, progress));
+        let _ = tx.send(ProgressUpdate::Status(msg, progress));
     };
 
-    send("Preparing package...".to_string(), 10);
+    let events_tx = tx.clone();
+    let events = SigningEvents::new(move |event: &SigningEvent| {
+        let _ = events_tx.send(ProgressUpdate::Event(event.clone()));
+    });
+
+    send("Extracting package...".to_string(), 10);
+
+    // extraction takes up 10-20%, only report when the percentage moves
+    let mut last_progress = 10;
+    let bundle = package
+        .get_package_bundle_cancellable(cancel, |written, total| {
+            let progress = 10 + (written * 10 / total.max(1)) as i32;
+            if progress != last_progress {
+                last_progress = progress;
+                send("Extracting package...".to_string(), progress);
+            }
+        })
+        .map_err(|e| e.to_string())?;
+
+    // bail out before touching the developer portal, encrypted apps crash on launch
+    if options.mode != SignerMode::None {
+        bundle.ensure_unencrypted().map_err(|e| e.to_string())?;
+    }
 
     match options.mode {
         SignerMode::Pem => {
@@ -283,6 +310,7 @@ pub(crate) async fn run_installation(
             .await
             .map_err(|e| e.to_string())?;
 
+            cancel.check().map_err(|e| e.to_string())?;
             send("Ensuring device is registered...".to_string(), 30);
 
             if let Some(dev) = &device {
@@ -292,11 +320,9 @@ pub(crate) async fn run_installation(
                     .map_err(|e| e.to_string())?;
             }
 
-            send("Extracting package...".to_string(), 50);
-
-            let mut signer = Signer::new(Some(identity), options.clone());
-
-            let bundle = package.get_package_bundle().map_err(|e| e.to_string())?;
+            let mut signer = Signer::new(Some(identity), options.clone())
+                .with_events(events.clone())
+                .with_cancellation(cancel.clone());
 
             send("Signing package...".to_string(), 70);
 
@@ -308,20 +334,21 @@ pub(crate) async fn run_installation(
                 .register_bundle(&bundle, &session, team_id, false)
                 .await
                 .map_err(|e| e.to_string())?;
-            signer
-                .sign_bundle(&bundle)
-                .await
-                .map_err(|e| e.to_string())?;
+            if let Err(e) = signer.sign_bundle(&bundle).await {
+                if matches!(e, plume_utils::Error::Cancelled) {
+                    signer.delete_created_app_ids(&session, team_id).await;
+                }
+                return Err(e.to_string());
+            }
 
             options = signer.options.clone();
+            registered = Some((signer, session, team_id.clone()));
             package_file = bundle;
         }
         SignerMode::Adhoc => {
-            send("Extracting package...".to_string(), 50);
-
-            let mut signer = Signer::new(None, options.clone());
-
-            let bundle = package.get_package_bundle().map_err(|e| e.to_string())?;
+            let mut signer = Signer::new(None, options.clone())
+                .with_events(events.clone())
+                .with_cancellation(cancel.clone());
 
             send("Signing package...".to_string(), 70);
 
@@ -338,31 +365,53 @@ pub(crate) async fn run_installation(
             package_file = bundle;
         }
         _ => {
-            send("Extracting package...".to_string(), 50);
-
-            let bundle = package.get_package_bundle().map_err(|e| e.to_string())?;
-
             package_file = bundle;
         }
     }
 
+    if let Err(e) = cancel.check() {
+        delete_registered_app_ids(&mut registered).await;
+        return Err(e.to_string());
+    }
+
     match options.install_mode {
         SignerInstallMode::Install => {
+            // the device only says "invalid signature", find out what exactly first
+            if options.mode != SignerMode::None {
+                send("Verifying signature...".to_string(), 70);
+                package_file.ensure_verified().map_err(|e| e.to_string())?;
+            }
+
             if let Some(dev) = &device {
                 if !dev.is_mac {
                     send("Sending to device...".to_string(), 70);
 
                     let tx_clone = tx.clone();
-                    dev.install_app(&package_file.bundle_dir(), move |progress: i32| {
-                        let tx = tx_clone.clone();
-                        // Some libraries expect this future to be processed.
-                        // We ensure it sends and resolves immediately.
-                        Box::pin(async move {
-                            let _ = tx.send(("Installing...".to_string(), 70 + (progress / 5)));
-                        })
-                    })
-                    .await
-                    .map_err(|e| format!("Install error: {}", e))?;
+                    let events = events.clone();
+                    let installed = dev
+                        .install_app_cancellable(
+                            &package_file.bundle_dir(),
+                            cancel,
+                            move |progress: i32| {
+                                let tx = tx_clone.clone();
+                                events.emit(SigningEventKind::InstallProgress { progress });
+                                // Some libraries expect this future to be processed.
+                                // We ensure it sends and resolves immediately.
+                                Box::pin(async move {
+                                    let _ = tx.send(ProgressUpdate::Status(
+                                        "Installing...".to_string(),
+                                        70 + (progress / 5),
+                                    ));
+                                })
+                            },
+                        )
+                        .await;
+                    if let Err(e) = installed {
+                        if cancel.is_cancelled() {
+                            delete_registered_app_ids(&mut registered).await;
+                        }
+                        return Err(format!("Install error: {}", e));
+                    }
 
                     if options.app.supports_pairing_file() {
                         if let (Some(custom_identifier), Some(pairing_file_bundle_path)) = (
@@ -392,7 +441,10 @@ pub(crate) async fn run_installation(
             send("Exporting...".to_string(), 90);
 
             let archive_path = package
-                .get_archive_based_on_path(&package_file.bundle_dir())
+                .get_archive_based_on_path(
+                    &package_file.bundle_dir(),
+                    &options.strip_package_entries,
+                )
                 .map_err(|e| e.to_string())?;
 
             let file = rfd::AsyncFileDialog::new()
@@ -500,6 +552,19 @@ pub(crate) async fn run_installation(
     Ok(())
 }
 
+/// Deletes the App IDs registered by [`run_installation`] once it's cancelled.
+async fn delete_registered_app_ids(
+    registered: &mut Option<(
+        plume_utils::Signer,
+        plume_core::developer::DeveloperSession,
+        String,
+    )>,
+) {
+    if let Some((mut signer, session, team_id)) = registered.take() {
+        signer.delete_created_app_ids(&session, &team_id).await;
+    }
+}
+
 #[allow(dead_code)]
 pub(crate) async fn export_certificate(account: plume_store::GsaAccount) -> Result<(), String> {
     use plume_core::{AnisetteConfiguration, CertificateIdentity, developer::DeveloperSession};
@@ -0,0 +1,84 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Args;
+
+use plume_utils::Bundle;
+
+use crate::commands::sign::{is_app_bundle, open_package};
+
+#[derive(Debug, Args)]
+#[command(arg_required_else_help = true)]
+pub struct DependenciesArgs {
+    /// Path to the app bundle or package to check (.app, .ipa, .tipa, .zip or .xcarchive)
+    #[arg(value_name = "PACKAGE")]
+    pub package: PathBuf,
+    /// App to check when the package contains several (will prompt if not provided)
+    #[arg(long, value_name = "APP")]
+    pub app: Option<String>,
+    /// Print every dependency, not just the unresolved ones
+    #[arg(long)]
+    pub all: bool,
+}
+
+pub async fn execute(args: DependenciesArgs) -> Result<()> {
+    let (bundle, package) = if is_app_bundle(&args.package) {
+        (Bundle::new(&args.package)?, None)
+    } else {
+        let pkg = open_package(&args.package, args.app.as_deref())?;
+        let bundle = pkg.get_package_bundle()?;
+        (bundle, Some(pkg))
+    };
+
+    let graph = bundle.dependency_graph()?;
+    let root = bundle
+        .bundle_dir()
+        .parent()
+        .unwrap_or(bundle.bundle_dir())
+        .to_path_buf();
+    let relative = |path: &PathBuf| {
+        path.strip_prefix(&root)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    };
+
+    if args.all {
+        let mut binaries: Vec<_> = graph.binaries.iter().collect();
+        binaries.sort_by(|a, b| a.0.cmp(b.0));
+
+        for (binary, dependencies) in binaries {
+            println!("{}", relative(binary));
+            for dependency in dependencies {
+                let status = match &dependency.resolved {
+                    Some(path) => relative(path),
+                    None if dependency.system => "system".to_string(),
+                    None if dependency.weak => "missing (weak)".to_string(),
+                    None => "MISSING".to_string(),
+                };
+                println!("    {} -> {}", dependency.name, status);
+            }
+        }
+    }
+
+    let unresolved = graph.unresolved();
+    let count = unresolved.len();
+    for (binary, dependency) in unresolved {
+        log::error!(
+            "Unresolved dependency in {}: {}",
+            relative(binary),
+            dependency.name
+        );
+    }
+
+    if let Some(pkg) = package {
+        pkg.remove_package_stage();
+    }
+
+    if count > 0 {
+        return Err(anyhow::anyhow!("{count} unresolved dependencies"));
+    }
+
+    log::info!("All dependencies resolve.");
+    Ok(())
+}
@@ -8,7 +8,9 @@ use idevice::{
     installation_proxy::InstallationProxyClient,
     usbmuxd::{UsbmuxdAddr, UsbmuxdConnection},
 };
-use plume_utils::{Device, Package, get_device_for_id};
+use plume_utils::{Device, get_device_for_id};
+
+use crate::commands::sign::{is_app_bundle, open_package};
 
 #[derive(Debug, Args)]
 #[command(arg_required_else_help = true)]
@@ -21,7 +23,7 @@ pub struct DeviceArgs {
         conflicts_with = "mac"
     )]
     pub udid: Option<String>,
-    /// Install app at specified path to device (.ipa, .app)
+    /// Install app at specified path to device (.ipa, .tipa, .zip, .xcarchive, .app)
     #[arg(short = 'i', long = "install", value_name = "PATH")]
     pub install: Option<PathBuf>,
     /// Install pairing record from specified path to device
@@ -70,8 +72,8 @@ pub async fn execute(args: DeviceArgs) -> Result<()> {
     if let Some(app_path) = args.install {
         let mut app_path = app_path;
 
-        if !app_path.is_dir() {
-            app_path = Package::new(app_path)?
+        if !is_app_bundle(&app_path) {
+            app_path = open_package(&app_path, None)?
                 .get_package_bundle()?
                 .bundle_dir()
                 .clone();
@@ -0,0 +1,53 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Args;
+
+use plume_utils::{Bundle, LintSeverity};
+
+use crate::commands::sign::{is_app_bundle, open_package};
+
+#[derive(Debug, Args)]
+#[command(arg_required_else_help = true)]
+pub struct LintArgs {
+    /// Path to the app bundle or package to check (.app, .ipa, .tipa, .zip or .xcarchive)
+    #[arg(value_name = "PACKAGE")]
+    pub package: PathBuf,
+    /// App to check when the package contains several (will prompt if not provided)
+    #[arg(long, value_name = "APP")]
+    pub app: Option<String>,
+}
+
+pub async fn execute(args: LintArgs) -> Result<()> {
+    let (bundle, package) = if is_app_bundle(&args.package) {
+        (Bundle::new(&args.package)?, None)
+    } else {
+        let pkg = open_package(&args.package, args.app.as_deref())?;
+        let bundle = pkg.get_package_bundle()?;
+        (bundle, Some(pkg))
+    };
+
+    let report = bundle.lint();
+
+    if let Some(pkg) = package {
+        pkg.remove_package_stage();
+    }
+
+    let report = report?;
+    for message in &report.messages {
+        match message.severity() {
+            LintSeverity::Error => log::error!("{message}"),
+            LintSeverity::Warning => log::warn!("{message}"),
+        }
+    }
+
+    let errors = report.errors().count();
+    if errors > 0 {
+        return Err(anyhow::anyhow!("{errors} lint errors"));
+    }
+
+    if report.is_empty() {
+        log::info!("No problems found.");
+    }
+    Ok(())
+}
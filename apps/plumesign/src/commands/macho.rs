@@ -19,9 +19,39 @@ pub struct MachArgs {
     /// Replace an existing dylib dependency
     #[arg(long, value_names = &["OLD", "NEW"], num_args = 2)]
     pub replace_dylib: Option<Vec<String>>,
+    /// List all rpaths
+    #[arg(long)]
+    pub list_rpaths: bool,
+    /// Add an rpath (e.g., @executable_path/Frameworks)
+    #[arg(long, value_name = "RPATH")]
+    pub add_rpath: Option<String>,
+    /// Remove an existing rpath
+    #[arg(long, value_name = "RPATH")]
+    pub remove_rpath: Option<String>,
+    /// Print the install name (LC_ID_DYLIB) of a dylib
+    #[arg(long)]
+    pub install_name: bool,
+    /// Set the install name (LC_ID_DYLIB) of a dylib (e.g., @rpath/MyLib.dylib)
+    #[arg(long, value_name = "INSTALL_NAME")]
+    pub set_install_name: Option<String>,
     /// Set the SDK version (e.g., 26.0.0)
     #[arg(long, value_name = "SDK_VERSION")]
     pub sdk_version: Option<String>,
+    /// Print the platform, minimum OS and SDK version of each slice
+    #[arg(long)]
+    pub build_version: bool,
+    /// Set the minimum OS version (e.g., 15.0)
+    #[arg(long, value_name = "VERSION")]
+    pub minimum_os_version: Option<String>,
+    /// Set the platform number (e.g., 2 for iOS, 6 for Mac Catalyst)
+    #[arg(long, value_name = "PLATFORM")]
+    pub platform: Option<u32>,
+    /// Print the embedded code signature of each slice
+    #[arg(long)]
+    pub signature: bool,
+    /// Remove the embedded code signature
+    #[arg(long)]
+    pub remove_signature: bool,
 }
 
 pub async fn execute(args: MachArgs) -> Result<()> {
@@ -39,6 +69,21 @@ pub async fn execute(args: MachArgs) -> Result<()> {
         }
     }
 
+    if let Some(rpath) = &args.add_rpath {
+        macho.add_rpath(rpath)?;
+        return Ok(());
+    }
+
+    if let Some(rpath) = &args.remove_rpath {
+        macho.remove_rpath(rpath)?;
+        return Ok(());
+    }
+
+    if let Some(install_name) = &args.set_install_name {
+        macho.set_install_name(install_name)?;
+        return Ok(());
+    }
+
     if args.list_dylibs {
         // TODO: add index argument
         let d = macho
@@ -53,11 +98,87 @@ pub async fn execute(args: MachArgs) -> Result<()> {
         return Ok(());
     }
 
+    if args.list_rpaths {
+        for path in macho.macho_file().nth_macho(0)?.rpaths()? {
+            println!("{path}");
+        }
+        return Ok(());
+    }
+
+    if args.install_name {
+        if let Some(name) = macho.macho_file().nth_macho(0)?.install_name()? {
+            println!("{name}");
+        }
+        return Ok(());
+    }
+
     if let Some(sdk_version) = &args.sdk_version {
         macho.replace_sdk_version(sdk_version)?;
         return Ok(());
     }
 
+    if let Some(minimum_os_version) = &args.minimum_os_version {
+        macho.set_minimum_os_version(minimum_os_version)?;
+        return Ok(());
+    }
+
+    if let Some(platform) = args.platform {
+        macho.set_platform(platform)?;
+        return Ok(());
+    }
+
+    if args.build_version {
+        for (index, version) in macho.build_versions()?.into_iter().enumerate() {
+            match version {
+                Some(v) => println!(
+                    "[{index}] platform {} minos {} sdk {}{}",
+                    v.platform,
+                    v.minos,
+                    v.sdk,
+                    if v.legacy { " (LC_VERSION_MIN)" } else { "" }
+                ),
+                None => println!("[{index}] no build version"),
+            }
+        }
+        return Ok(());
+    }
+
+    if args.remove_signature {
+        macho.remove_code_signature()?;
+        return Ok(());
+    }
+
+    if args.signature {
+        for (index, info) in macho.signature_info()?.into_iter().enumerate() {
+            let Some(info) = info else {
+                println!("[{index}] not signed");
+                continue;
+            };
+
+            println!("[{index}] identifier: {}", info.identifier);
+            println!(
+                "    team id: {}",
+                info.team_id.as_deref().unwrap_or("none (ad-hoc)")
+            );
+            println!(
+                "    certificate: {}",
+                info.certificate_subject.as_deref().unwrap_or("none")
+            );
+            for (digest_type, cdhash) in &info.cdhashes {
+                println!("    cdhash ({digest_type}): {cdhash}");
+            }
+            for requirement in &info.requirements {
+                println!("    requirement: {requirement}");
+            }
+            if let Some(ent) = &info.entitlements {
+                let mut buf = Vec::new();
+                plist::Value::Dictionary(ent.clone()).to_writer_xml(&mut buf)?;
+                println!("    entitlements:\n{}", String::from_utf8(buf)?);
+            }
+        }
+        return Ok(());
+    }
+
     let entitlements = macho.entitlements();
     if args.entitlements {
         if let Some(ent) = entitlements {
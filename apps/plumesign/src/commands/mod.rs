@@ -1,9 +1,12 @@
 use clap::{Parser, Subcommand};
 
 pub mod account;
+pub mod dependencies;
 pub mod device;
+pub mod lint;
 pub mod macho;
 pub mod sign;
+pub mod verify;
 
 #[derive(Debug, Parser)]
 #[command(
@@ -25,6 +28,13 @@ pub enum Commands {
     Sign(sign::SignArgs),
     /// Inspect Mach-O binaries
     MachO(macho::MachArgs),
+    /// Check that every binary's dylib dependencies resolve inside the bundle
+    #[command(alias = "deps")]
+    Dependencies(dependencies::DependenciesArgs),
+    /// Check a bundle for problems that would make the install fail
+    Lint(lint::LintArgs),
+    /// Check that every component of a signed bundle is sealed by its signature
+    Verify(verify::VerifyArgs),
     /// Manage Apple Developer account authentication
     Account(account::AccountArgs),
     /// Device management commands
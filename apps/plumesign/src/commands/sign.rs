@@ -1,10 +1,18 @@
-use std::path::PathBuf;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
 use anyhow::Result;
 use clap::Args;
+use dialoguer::Select;
 
 use plume_core::{CertificateIdentity, MobileProvision};
-use plume_utils::{Bundle, Package, Signer, SignerMode, SignerOptions};
+use plume_utils::{
+    Bundle, CancellationToken, ComponentEntitlements, InfoPlistPatch, Package, Signer,
+    SignerFeatures, SignerMode, SignerOptions, SigningEvent, SigningEventKind, SigningEvents,
+    UrlSchemeRewrite,
+};
 
 use crate::{
     commands::{
@@ -17,9 +25,12 @@ use crate::{
 #[derive(Debug, Args)]
 #[command(arg_required_else_help = true)]
 pub struct SignArgs {
-    /// Path to the app bundle or package to sign (.app or .ipa)
+    /// Path to the app bundle or package to sign (.app, .ipa, .tipa, .zip or .xcarchive)
     #[arg(long, short, value_name = "PACKAGE")]
     pub package: PathBuf,
+    /// App to sign when the package contains several (will prompt if not provided)
+    #[arg(long, value_name = "APP")]
+    pub app: Option<String>,
     /// PEM files for certificate and private key
     #[arg(long = "pem", value_name = "PEM", num_args = 1..)]
     pub pem_files: Option<Vec<PathBuf>>,
@@ -35,12 +46,24 @@ pub struct SignArgs {
     /// Custom bundle name to set
     #[arg(long = "custom-name", value_name = "NAME")]
     pub name: Option<String>,
+    /// Custom name for a single locale, e.g. ja=アプリ (can be repeated)
+    #[arg(long = "custom-localized-name", value_name = "LOCALE=NAME", value_parser = parse_localized_name)]
+    pub localized_names: Vec<(String, String)>,
+    /// Icon image to use for the app, rendered at every iPhone and iPad size
+    #[arg(long = "custom-icon", value_name = "IMAGE")]
+    pub icon: Option<PathBuf>,
+    /// Alternate icon the app can switch to, e.g. Dark=dark.png (can be repeated)
+    #[arg(long = "alternate-icon", value_name = "NAME=IMAGE", value_parser = parse_alternate_icon)]
+    pub alternate_icons: Vec<(String, PathBuf)>,
     /// Custom bundle version to set
     #[arg(long = "custom-version", value_name = "VERSION")]
     pub version: Option<String>,
     /// Perform ad-hoc signing (no certificate required)
     #[arg(long, short, num_args = 1..)]
     pub tweaks: Option<Vec<PathBuf>>,
+    /// Minimum OS version to set in every Info.plist and binary
+    #[arg(long = "minimum-os-version", value_name = "VERSION")]
+    pub minimum_os_version: Option<String>,
     /// Register device and install after signing
     #[arg(long)]
     pub register_and_install: bool,
@@ -50,6 +73,38 @@ pub struct SignArgs {
     /// Output path for signed .ipa (only for .ipa input)
     #[arg(long, short, value_name = "OUTPUT")]
     pub output: Option<PathBuf>,
+    /// Top-level package entries to leave out of the output (e.g. Symbols, SwiftSupport)
+    #[arg(long = "strip", value_name = "ENTRY", num_args = 1..)]
+    pub strip_package_entries: Vec<String>,
+    /// Nested apps or extensions to remove, relative to the app (e.g. PlugIns/Widget.appex)
+    #[arg(long = "remove-component", value_name = "COMPONENT", num_args = 1..)]
+    pub remove_components: Vec<String>,
+    /// Remove every localization except Base, the development region and these locales
+    #[arg(long = "keep-localization", value_name = "LOCALE", num_args = 1..)]
+    pub keep_localizations: Option<Vec<String>>,
+    /// Entitlements to add to one component's, by path relative to the app (. for the app
+    /// itself) or bundle ID, e.g. PlugIns/Widget.appex=widget.plist (can be repeated)
+    #[arg(long = "component-entitlements", value_name = "COMPONENT=PLIST", value_parser = parse_component_entitlements)]
+    pub component_entitlements: Vec<(String, PathBuf)>,
+    /// Like --component-entitlements, but signs the component with only these entitlements
+    #[arg(long = "replace-component-entitlements", value_name = "COMPONENT=PLIST", value_parser = parse_component_entitlements)]
+    pub replace_component_entitlements: Vec<(String, PathBuf)>,
+    /// Info.plist patch document (plist or JSON) with set, delete and append operations
+    /// (can be repeated)
+    #[arg(long = "info-plist-patch", value_name = "PATCH")]
+    pub info_plist_patches: Vec<PathBuf>,
+    /// Remove UISupportedDevices so the app installs on any device
+    #[arg(long)]
+    pub remove_supported_devices: bool,
+    /// Remove the URL schemes of the app and its extensions
+    #[arg(long)]
+    pub remove_url_schemes: bool,
+    /// Append to every URL scheme, so a second copy of an app gets its own (e.g. -2)
+    #[arg(long = "url-scheme-suffix", value_name = "SUFFIX", conflicts_with_all = ["remove_url_schemes", "rename_url_schemes"])]
+    pub url_scheme_suffix: Option<String>,
+    /// Rename a single URL scheme, e.g. myapp=myapp2 (can be repeated)
+    #[arg(long = "rename-url-scheme", value_name = "OLD=NEW", value_parser = parse_url_scheme, conflicts_with = "remove_url_schemes")]
+    pub rename_url_schemes: Vec<(String, String)>,
     /// Install to connected Mac (arm64 only)
     #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
     #[arg(short = 'm', long = "mac", value_name = "MAC", conflicts_with = "udid")]
@@ -57,21 +112,50 @@ pub struct SignArgs {
 }
 
 pub async fn execute(args: SignArgs) -> Result<()> {
-    if !args.package.is_dir() && !args.apple_id && args.output.is_none() {
+    let in_place = is_app_bundle(&args.package);
+
+    if !in_place && !args.apple_id && args.output.is_none() {
         return Err(anyhow::anyhow!(
             "-o/--output is required when signing an .ipa without --apple-id (ad-hoc mode)."
         ));
     }
 
+    let mut info_plist_patches = Vec::new();
+    for path in &args.info_plist_patches {
+        info_plist_patches.extend(InfoPlistPatch::load(path)?);
+    }
+
     let mut options = SignerOptions {
         custom_identifier: args.bundle_identifier,
         custom_name: args.name,
+        custom_localized_names: args.localized_names.into_iter().collect(),
         custom_version: args.version,
+        custom_icon: args.icon,
+        custom_alternate_icons: args.alternate_icons,
         tweaks: args.tweaks,
+        minimum_os_version: args.minimum_os_version,
+        remove_components: args.remove_components,
+        keep_localizations: args.keep_localizations,
+        component_entitlements: component_entitlements(
+            args.component_entitlements,
+            args.replace_component_entitlements,
+        ),
+        info_plist_patches,
+        url_scheme_rewrite: url_scheme_rewrite(args.url_scheme_suffix, args.rename_url_schemes),
+        features: SignerFeatures {
+            remove_supported_devices: args.remove_supported_devices,
+            remove_url_schemes: args.remove_url_schemes,
+            ..Default::default()
+        },
+        // extracted packages land somewhere new every time, only bundles signed in place repeat
+        signature_cache: in_place.then(|| get_data_path().join("signature_cache")),
         ..Default::default()
     };
 
-    let (bundle, package) = if args.package.is_dir() {
+    let cancel = CancellationToken::new();
+    cancel_on_ctrl_c(cancel.clone());
+
+    let (bundle, package) = if in_place {
         log::warn!("⚠️  Signing bundle in place: {}", args.package.display());
         if args.output.is_some() {
             log::warn!(
@@ -80,120 +164,168 @@ pub async fn execute(args: SignArgs) -> Result<()> {
         }
         (Bundle::new(&args.package)?, None)
     } else {
-        let pkg = Package::new(args.package.clone())?;
-        let bundle = pkg.get_package_bundle()?;
+        let pkg = open_package(&args.package, args.app.as_deref())?;
+        let bundle = pkg.get_package_bundle_cancellable(&cancel, |_, _| {})?;
         (bundle, Some(pkg))
     };
 
-    let (mut signer, team_id_opt) = if let Some(ref pem_files) = args.pem_files {
-        let cert_identity = CertificateIdentity::new_with_paths(Some(pem_files.clone())).await?;
+    let output = args.output;
+    let strip_package_entries = args.strip_package_entries;
 
-        options.mode = SignerMode::Pem;
-        (Signer::new(Some(cert_identity), options), None)
-    } else if args.apple_id {
-        let session = get_authenticated_account().await?;
-        let team_id = teams(&session).await?;
-        let cert_identity =
-            CertificateIdentity::new_with_session(&session, get_data_path(), None, &team_id, false)
-                .await?;
+    // anything failing past this point leaves a stage directory behind otherwise
+    let result: Result<()> = async {
+        // refuse encrypted apps before any developer portal calls
+        bundle.ensure_unencrypted()?;
 
-        options.mode = SignerMode::Pem;
-        (
-            Signer::new(Some(cert_identity), options),
-            Some((session, team_id)),
-        )
-    } else {
-        options.mode = SignerMode::Adhoc;
-        (Signer::new(None, options), None)
-    };
+        let (mut signer, team_id_opt) = if let Some(ref pem_files) = args.pem_files {
+            let cert_identity =
+                CertificateIdentity::new_with_paths(Some(pem_files.clone())).await?;
 
-    if let Some(provision_path) = args.provisioning_files {
-        let prov = MobileProvision::load_with_path(&provision_path)?;
-        signer.provisioning_files.push(prov.clone());
-    }
+            options.mode = SignerMode::Pem;
+            (Signer::new(Some(cert_identity), options), None)
+        } else if args.apple_id {
+            let session = get_authenticated_account().await?;
+            let team_id = teams(&session).await?;
+            let cert_identity = CertificateIdentity::new_with_session(
+                &session,
+                get_data_path(),
+                None,
+                &team_id,
+                false,
+            )
+            .await?;
 
-    let device = if args.register_and_install {
-        #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
-        {
-            if args.mac {
-                use plume_utils::Device;
-
-                Some(Device {
-                    name: "My Mac".to_string(),
-                    udid: String::new(),
-                    device_id: 0,
-                    usbmuxd_device: None,
-                    is_mac: true,
-                })
-            } else {
-                Some(select_device(args.udid).await?)
-            }
-        }
-        #[cfg(not(all(target_os = "macos", target_arch = "aarch64")))]
-        {
-            Some(select_device(args.udid).await?)
-        }
-    } else {
-        None
-    };
+            options.mode = SignerMode::Pem;
+            (
+                Signer::new(Some(cert_identity), options),
+                Some((session, team_id)),
+            )
+        } else {
+            options.mode = SignerMode::Adhoc;
+            (Signer::new(None, options), None)
+        };
 
-    if let Some((session, team_id)) = team_id_opt {
-        signer
-            .modify_bundle(&bundle, &Some(team_id.clone()))
-            .await?;
+        let events = SigningEvents::new(|event: &SigningEvent| {
+            log::info!("[{:>6.1}s] {event}", event.elapsed.as_secs_f64());
+        });
+        signer = signer
+            .with_events(events.clone())
+            .with_cancellation(cancel.clone());
 
-        if let Some(ref dev) = device {
-            log::info!("Registering device: {} ({})", dev.name, dev.udid);
-            session
-                .qh_ensure_device(&team_id, &dev.name, &dev.udid)
-                .await?;
-        }
+        let on_progress = move |progress| {
+            events.emit(SigningEventKind::InstallProgress { progress });
+            async {}
+        };
 
-        signer
-            .register_bundle(&bundle, &session, &team_id, false)
-            .await?;
-        signer.sign_bundle(&bundle).await?;
+        if let Some(provision_path) = args.provisioning_files {
+            let prov = MobileProvision::load_with_path(&provision_path)?;
+            signer.provisioning_files.push(prov.clone());
+        }
 
-        if let Some(dev) = device {
-            log::info!("Installing to device: {}", dev.name);
+        let device = if args.register_and_install {
             #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
-            if args.mac {
-                plume_utils::install_app_mac(&bundle.bundle_dir()).await?;
-            } else {
-                dev.install_app(bundle.bundle_dir(), |progress| async move {
-                    log::info!("Installation progress: {}%", progress);
-                })
-                .await?;
-            }
+            {
+                if args.mac {
+                    use plume_utils::Device;
 
+                    Some(Device {
+                        name: "My Mac".to_string(),
+                        udid: String::new(),
+                        device_id: 0,
+                        usbmuxd_device: None,
+                        is_mac: true,
+                    })
+                } else {
+                    Some(select_device(args.udid).await?)
+                }
+            }
             #[cfg(not(all(target_os = "macos", target_arch = "aarch64")))]
             {
-                dev.install_app(bundle.bundle_dir(), |progress| async move {
-                    log::info!("Installation progress: {}%", progress);
-                })
+                Some(select_device(args.udid).await?)
+            }
+        } else {
+            None
+        };
+
+        if let Some((session, team_id)) = team_id_opt {
+            signer
+                .modify_bundle(&bundle, &Some(team_id.clone()))
                 .await?;
+
+            if let Some(ref dev) = device {
+                log::info!("Registering device: {} ({})", dev.name, dev.udid);
+                session
+                    .qh_ensure_device(&team_id, &dev.name, &dev.udid)
+                    .await?;
+            }
+
+            signer
+                .register_bundle(&bundle, &session, &team_id, false)
+                .await?;
+
+            #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
+            let mac = args.mac;
+            let installed: Result<()> = async {
+                signer.sign_bundle(&bundle).await?;
+
+                if let Some(dev) = device {
+                    bundle.ensure_verified()?;
+                    log::info!("Installing to device: {}", dev.name);
+                    #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
+                    if mac {
+                        plume_utils::install_app_mac(&bundle.bundle_dir()).await?;
+                    } else {
+                        dev.install_app_cancellable(bundle.bundle_dir(), &cancel, on_progress)
+                            .await?;
+                    }
+
+                    #[cfg(not(all(target_os = "macos", target_arch = "aarch64")))]
+                    {
+                        dev.install_app_cancellable(bundle.bundle_dir(), &cancel, on_progress)
+                            .await?;
+                    }
+
+                    log::info!("Installation complete!");
+                }
+
+                Ok(())
+            }
+            .await;
+
+            // App IDs registered for a run that didn't finish would only use up the limit
+            if installed.is_err() && cancel.is_cancelled() {
+                signer.delete_created_app_ids(&session, &team_id).await;
             }
+            installed?;
+        } else {
+            signer.modify_bundle(&bundle, &None).await?;
+            signer.sign_bundle(&bundle).await?;
+
+            if let Some(dev) = device {
+                bundle.ensure_verified()?;
+                log::info!("Installing to device: {}", dev.name);
+                dev.install_app_cancellable(bundle.bundle_dir(), &cancel, on_progress)
+                    .await?;
 
-            log::info!("Installation complete!");
+                log::info!("Installation complete!");
+            }
         }
-    } else {
-        signer.modify_bundle(&bundle, &None).await?;
-        signer.sign_bundle(&bundle).await?;
-
-        if let Some(dev) = device {
-            log::info!("Installing to device: {}", dev.name);
-            dev.install_app(bundle.bundle_dir(), |progress| async move {
-                log::info!("Installation progress: {}%", progress);
-            })
-            .await?;
 
-            log::info!("Installation complete!");
+        Ok(())
+    }
+    .await;
+
+    if let Err(e) = result {
+        if let Some(pkg) = package {
+            pkg.remove_package_stage();
         }
+        return Err(e);
     }
 
     if let Some(pkg) = package {
-        if let Some(output_path) = args.output {
-            let archived_path = pkg.get_archive_based_on_path(&args.package.clone())?;
+        if let Some(output_path) = output {
+            let archived_path =
+                pkg.get_archive_based_on_path(bundle.bundle_dir(), &strip_package_entries)?;
             tokio::fs::copy(&archived_path, &output_path).await?;
             log::info!("Saved signed package to: {}", output_path.display());
             if std::env::var("PLUME_DELETE_AFTER_FINISHED").is_err() {
@@ -209,3 +341,103 @@ pub async fn execute(args: SignArgs) -> Result<()> {
 
     Ok(())
 }
+
+/// Cancels at the next checkpoint on the first Ctrl-C, exits on the second.
+fn cancel_on_ctrl_c(cancel: CancellationToken) {
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_err() {
+            return;
+        }
+        log::warn!("Cancelling, press Ctrl-C again to quit immediately");
+        cancel.cancel();
+
+        if tokio::signal::ctrl_c().await.is_ok() {
+            std::process::exit(130);
+        }
+    });
+}
+
+/// `.app` directories are signed in place, everything else goes through [`Package`].
+pub fn is_app_bundle(path: &Path) -> bool {
+    path.is_dir() && !Package::is_supported_path(path)
+}
+
+/// Opens a package, asking which app to use when it contains more than one.
+pub fn open_package(path: &Path, app: Option<&str>) -> Result<Package> {
+    let mut package = Package::new(path.to_path_buf())?;
+    let apps = package.apps();
+
+    if apps.len() > 1 {
+        let index = match app {
+            Some(name) => apps
+                .iter()
+                .position(|a| a == name || a.trim_end_matches(".app") == name)
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "No app named {name} in package, available: {}",
+                        apps.join(", ")
+                    )
+                })?,
+            None => Select::new()
+                .with_prompt("Select an app from the package")
+                .items(&apps)
+                .default(0)
+                .interact()?,
+        };
+        package.select_app(index)?;
+    }
+
+    Ok(package)
+}
+
+fn parse_localized_name(value: &str) -> Result<(String, String), String> {
+    value
+        .split_once('=')
+        .map(|(locale, name)| (locale.to_string(), name.to_string()))
+        .ok_or_else(|| format!("expected LOCALE=NAME, got {value}"))
+}
+
+fn parse_url_scheme(value: &str) -> Result<(String, String), String> {
+    value
+        .split_once('=')
+        .map(|(old, new)| (old.to_string(), new.to_string()))
+        .ok_or_else(|| format!("expected OLD=NEW, got {value}"))
+}
+
+fn url_scheme_rewrite(
+    suffix: Option<String>,
+    renames: Vec<(String, String)>,
+) -> Option<UrlSchemeRewrite> {
+    match suffix {
+        Some(suffix) => Some(UrlSchemeRewrite::Suffix(suffix)),
+        None if renames.is_empty() => None,
+        None => Some(UrlSchemeRewrite::Replace(renames.into_iter().collect())),
+    }
+}
+
+fn parse_component_entitlements(value: &str) -> Result<(String, PathBuf), String> {
+    value
+        .split_once('=')
+        .map(|(component, plist)| (component.to_string(), PathBuf::from(plist)))
+        .ok_or_else(|| format!("expected COMPONENT=PLIST, got {value}"))
+}
+
+fn component_entitlements(
+    merge: Vec<(String, PathBuf)>,
+    replace: Vec<(String, PathBuf)>,
+) -> HashMap<String, ComponentEntitlements> {
+    let merge = merge.into_iter().map(|entry| (entry, false));
+    let replace = replace.into_iter().map(|entry| (entry, true));
+
+    merge
+        .chain(replace)
+        .map(|((component, path), replace)| (component, ComponentEntitlements { path, replace }))
+        .collect()
+}
+
+fn parse_alternate_icon(value: &str) -> Result<(String, PathBuf), String> {
+    value
+        .split_once('=')
+        .map(|(name, image)| (name.to_string(), PathBuf::from(image)))
+        .ok_or_else(|| format!("expected NAME=IMAGE, got {value}"))
+}
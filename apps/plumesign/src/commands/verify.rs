@@ -0,0 +1,50 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Args;
+
+use plume_utils::Bundle;
+
+use crate::commands::sign::{is_app_bundle, open_package};
+
+#[derive(Debug, Args)]
+#[command(arg_required_else_help = true)]
+pub struct VerifyArgs {
+    /// Path to the signed app bundle or package (.app, .ipa, .tipa, .zip or .xcarchive)
+    #[arg(value_name = "PACKAGE")]
+    pub package: PathBuf,
+    /// App to check when the package contains several (will prompt if not provided)
+    #[arg(long, value_name = "APP")]
+    pub app: Option<String>,
+}
+
+pub async fn execute(args: VerifyArgs) -> Result<()> {
+    let (bundle, package) = if is_app_bundle(&args.package) {
+        (Bundle::new(&args.package)?, None)
+    } else {
+        let pkg = open_package(&args.package, args.app.as_deref())?;
+        let bundle = pkg.get_package_bundle()?;
+        (bundle, Some(pkg))
+    };
+
+    let report = bundle.verify();
+
+    if let Some(pkg) = package {
+        pkg.remove_package_stage();
+    }
+
+    let report = report?;
+    for message in &report.messages {
+        log::error!("{message}");
+    }
+
+    if !report.is_valid() {
+        return Err(anyhow::anyhow!(
+            "{} signature problems",
+            report.messages.len()
+        ));
+    }
+
+    log::info!("Signature is valid.");
+    Ok(())
+}
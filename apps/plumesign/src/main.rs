@@ -17,6 +17,9 @@ async fn main() -> anyhow::Result<()> {
     match cli.command {
         Commands::Sign(args) => commands::sign::execute(args).await?,
         Commands::MachO(args) => commands::macho::execute(args).await?,
+        Commands::Dependencies(args) => commands::dependencies::execute(args).await?,
+        Commands::Lint(args) => commands::lint::execute(args).await?,
+        Commands::Verify(args) => commands::verify::execute(args).await?,
         Commands::Account(args) => commands::account::execute(args).await?,
         Commands::Device(args) => commands::device::execute(args).await?,
     }
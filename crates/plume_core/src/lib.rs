@@ -2,11 +2,16 @@ pub mod auth;
 pub mod developer;
 mod utils;
 
-pub use apple_codesign::{AppleCodesignError, SettingsScope, SigningSettings, UnifiedSigner};
+pub use apple_codesign::{
+    AppleCodesignError, MachFile, SettingsScope, SigningSettings, UnifiedSigner,
+};
 
 pub use omnisette::AnisetteConfiguration;
 
-pub use utils::{CertificateIdentity, MachO, MachOExt, MobileProvision};
+pub use utils::{
+    BuildVersion, CertificateIdentity, EncryptionInfo, MachO, MachOExt, MobileProvision,
+    SignatureInfo, SignatureMismatch,
+};
 
 use thiserror::Error as ThisError;
 #[derive(Debug, ThisError)]
@@ -36,6 +41,10 @@ pub enum Error {
     Bad2faCode,
     #[error("Failed to parse")]
     Parse, // TODO: better parsing errors
+    #[error("Not enough room for load commands ({required} bytes needed, {available} available)")]
+    MachOLoadCommandSpace { required: usize, available: usize },
+    #[error("Platform {0} has no LC_VERSION_MIN_* command to rewrite this binary's to")]
+    UnsupportedPlatform(u32),
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
     #[error("Plist error: {0}")]
@@ -24,7 +24,8 @@ pub(crate) const MACHINE_NAME: &str = "AltStore";
 
 pub struct CertificateIdentity {
     pub cert: Option<CapturedX509Certificate>,
-    pub key: Option<Box<dyn PrivateKey>>,
+    // Send + Sync so nested bundles can be signed in parallel with one identity
+    pub key: Option<Box<dyn PrivateKey + Send + Sync>>,
     pub machine_id: Option<String>,
     pub serial_number: Option<String>,
     pub p12_data: Option<Vec<u8>>,
@@ -1,19 +1,49 @@
 use std::fs;
 use std::path::Path;
 
-use apple_codesign::{MachFile, MachOBinary, UniversalBinaryBuilder};
+use apple_codesign::{
+    MachFile, MachOBinary, UniversalBinaryBuilder,
+    embedded_signature::{Blob, CodeSigningSlot},
+};
 use goblin::mach::{
     MachO as GoblinMachO,
-    cputype::CPU_TYPE_ARM64,
     load_command::{
-        CommandVariant, LC_LAZY_LOAD_DYLIB, LC_LOAD_DYLIB, LC_LOAD_UPWARD_DYLIB,
-        LC_LOAD_WEAK_DYLIB, LC_REEXPORT_DYLIB,
+        CommandVariant, LC_BUILD_VERSION, LC_CODE_SIGNATURE, LC_DYLIB_CODE_SIGN_DRS, LC_ID_DYLIB,
+        LC_LAZY_LOAD_DYLIB, LC_LINKER_OPTIMIZATION_HINT, LC_LOAD_DYLIB, LC_LOAD_UPWARD_DYLIB,
+        LC_LOAD_WEAK_DYLIB, LC_REEXPORT_DYLIB, LC_RPATH, LC_SOURCE_VERSION,
+        LC_VERSION_MIN_IPHONEOS, LC_VERSION_MIN_MACOSX, LC_VERSION_MIN_TVOS,
+        LC_VERSION_MIN_WATCHOS, PLATFORM_IOS, PLATFORM_MACOS, PLATFORM_TVOS, PLATFORM_WATCHOS,
     },
 };
 use plist::{Dictionary, Value};
 
 use crate::Error;
 
+const DYLIB_COMMANDS: &[u32] = &[
+    LC_LOAD_DYLIB,
+    LC_LOAD_WEAK_DYLIB,
+    LC_REEXPORT_DYLIB,
+    LC_LAZY_LOAD_DYLIB,
+    LC_LOAD_UPWARD_DYLIB,
+];
+
+/// Commands dyld doesn't need at runtime, dropped when we run out of header space.
+/// The data the linkedit ones point at is cut out of `__LINKEDIT` with them.
+const DISPOSABLE_COMMANDS: &[u32] = &[
+    LC_DYLIB_CODE_SIGN_DRS,
+    LC_SOURCE_VERSION,
+    LC_LINKER_OPTIMIZATION_HINT,
+];
+
+/// sizeof(linkedit_data_command), the signer has to add one to unsigned binaries.
+const CODE_SIGNATURE_COMMAND_SIZE: usize = 16;
+
+/// `segname` of `__LINKEDIT`, NUL padded like in the segment command.
+const LINKEDIT_SEGNAME: [u8; 16] = *b"__LINKEDIT\0\0\0\0\0\0";
+
+/// codesign rounds the vmsize of `__LINKEDIT` up to 16 KiB.
+const LINKEDIT_VMSIZE_ALIGNMENT: u64 = 0x4000;
+
 /// Represents a Mach-O file and its entitlements.
 pub struct MachO {
     #[allow(dead_code)]
@@ -100,6 +130,105 @@ impl MachO {
         Ok(())
     }
 
+    pub fn add_rpath(&mut self, path: &str) -> Result<(), Error> {
+        let machos = self.macho_file.iter_macho_mut();
+        for macho in machos {
+            macho.add_rpath(path)?;
+        }
+        self.write_changes()?;
+        Ok(())
+    }
+
+    pub fn remove_rpath(&mut self, path: &str) -> Result<(), Error> {
+        let machos = self.macho_file.iter_macho_mut();
+        for macho in machos {
+            macho.remove_rpath(path)?;
+        }
+        self.write_changes()?;
+        Ok(())
+    }
+
+    pub fn set_install_name(&mut self, name: &str) -> Result<(), Error> {
+        let machos = self.macho_file.iter_macho_mut();
+        for macho in machos {
+            macho.set_install_name(name)?;
+        }
+        self.write_changes()?;
+        Ok(())
+    }
+
+    /// Encryption info of every slice, `None` for slices without `LC_ENCRYPTION_INFO(_64)`.
+    pub fn encryption_info(&self) -> Result<Vec<Option<EncryptionInfo>>, Error> {
+        self.macho_file
+            .iter_macho()
+            .map(|macho| macho.encryption_info())
+            .collect()
+    }
+
+    /// Whether any slice is still FairPlay encrypted, these crash on launch once re-signed.
+    pub fn is_encrypted(&self) -> Result<bool, Error> {
+        Ok(self
+            .encryption_info()?
+            .iter()
+            .flatten()
+            .any(EncryptionInfo::is_encrypted))
+    }
+
+    /// Build version of every slice, `None` for slices without one.
+    pub fn build_versions(&self) -> Result<Vec<Option<BuildVersion>>, Error> {
+        self.macho_file
+            .iter_macho()
+            .map(|macho| macho.build_version())
+            .collect()
+    }
+
+    pub fn set_minimum_os_version(&mut self, new_version: &str) -> Result<(), Error> {
+        let machos = self.macho_file.iter_macho_mut();
+        for macho in machos {
+            macho.set_minimum_os_version(new_version)?;
+        }
+        self.write_changes()?;
+        Ok(())
+    }
+
+    pub fn set_platform(&mut self, platform: u32) -> Result<(), Error> {
+        let machos = self.macho_file.iter_macho_mut();
+        for macho in machos {
+            macho.set_platform(platform)?;
+        }
+        self.write_changes()?;
+        Ok(())
+    }
+
+    pub fn signature_info(&self) -> Result<Vec<Option<SignatureInfo>>, Error> {
+        self.macho_file
+            .iter_macho()
+            .map(|macho| macho.signature_info())
+            .collect()
+    }
+
+    /// Signature mismatches of every slice, `None` for unsigned slices. See
+    /// [`MachOExt::verify_signature`].
+    pub fn verify_signature(
+        &self,
+        info_plist: Option<&[u8]>,
+        code_resources: Option<&[u8]>,
+    ) -> Result<Vec<Option<Vec<SignatureMismatch>>>, Error> {
+        self.macho_file
+            .iter_macho()
+            .map(|macho| macho.verify_signature(info_plist, code_resources))
+            .collect()
+    }
+
+    pub fn remove_code_signature(&mut self) -> Result<(), Error> {
+        let machos = self.macho_file.iter_macho_mut();
+        for macho in machos {
+            macho.remove_code_signature()?;
+        }
+        self.write_changes()?;
+        Ok(())
+    }
+
     pub fn replace_sdk_version(&mut self, new_version: &str) -> Result<(), Error> {
         let machos = self.macho_file.iter_macho_mut();
         for macho in machos {
@@ -110,14 +239,111 @@ impl MachO {
     }
 }
 
+/// Contents of a slice's `LC_ENCRYPTION_INFO(_64)` command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncryptionInfo {
+    /// File offset of the encrypted range.
+    pub cryptoff: u32,
+    /// Size of the encrypted range.
+    pub cryptsize: u32,
+    /// Encryption system, 0 means not encrypted.
+    pub cryptid: u32,
+}
+
+impl EncryptionInfo {
+    pub fn is_encrypted(&self) -> bool {
+        self.cryptid != 0
+    }
+}
+
+/// Platform and versions from `LC_BUILD_VERSION` or a legacy `LC_VERSION_MIN_*` command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildVersion {
+    /// `PLATFORM_*` value, legacy commands are mapped to their platform.
+    pub platform: u32,
+    /// Minimum OS version, e.g. `15.0`.
+    pub minos: String,
+    /// SDK version, e.g. `18.2`.
+    pub sdk: String,
+    /// Whether this came from a legacy `LC_VERSION_MIN_*` command.
+    pub legacy: bool,
+}
+
+/// Details of a slice's embedded code signature.
+#[derive(Debug, Clone)]
+pub struct SignatureInfo {
+    /// Identifier from the code directory, usually the bundle identifier.
+    pub identifier: String,
+    /// Team identifier, `None` for ad-hoc signatures.
+    pub team_id: Option<String>,
+    /// Truncated CDHash of every code directory, as `(digest type, hex)`.
+    pub cdhashes: Vec<(String, String)>,
+    /// Common name of the signing certificate, `None` for ad-hoc signatures.
+    pub certificate_subject: Option<String>,
+    /// Code requirements, e.g. `designated => identifier "..."`.
+    pub requirements: Vec<String>,
+    /// Entitlements embedded in the signature.
+    pub entitlements: Option<Dictionary>,
+}
+
+/// Something a slice's code directory doesn't agree with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureMismatch {
+    /// A page of the binary hashes differently, by page index.
+    CodePage(usize),
+    /// The binary has more or fewer pages than the code directory lists.
+    PageCount { expected: usize, actual: usize },
+    /// A special slot (`Info.plist`, `CodeResources`, entitlements, requirements)
+    /// hashes differently.
+    Slot(String),
+    /// The code directory has a hash for a slot whose data is gone.
+    MissingSlot(String),
+    /// Slot data exists but the code directory doesn't cover it.
+    UnsealedSlot(String),
+}
+
+impl std::fmt::Display for SignatureMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignatureMismatch::CodePage(page) => write!(f, "code page {page} was modified"),
+            SignatureMismatch::PageCount { expected, actual } => {
+                write!(
+                    f,
+                    "code directory lists {expected} pages, binary has {actual}"
+                )
+            }
+            SignatureMismatch::Slot(slot) => write!(f, "{slot} was modified"),
+            SignatureMismatch::MissingSlot(slot) => write!(f, "{slot} is sealed but missing"),
+            SignatureMismatch::UnsealedSlot(slot) => write!(f, "{slot} is not sealed"),
+        }
+    }
+}
+
 #[allow(dead_code)]
 pub trait MachOExt {
     fn embedded_entitlements(&self) -> Result<Option<Dictionary>, Error>;
     fn dylib_load_paths(&self) -> Result<Vec<String>, Error>;
+    fn weak_dylib_load_paths(&self) -> Result<Vec<String>, Error>;
     fn add_dylib_load_path(&mut self, path: &str) -> Result<(), Error>;
     fn remove_dylib_load_path(&mut self, path: &str) -> Result<(), Error>;
     fn replace_dylib_load_path(&mut self, old_path: &str, new_path: &str) -> Result<(), Error>;
     fn replace_sdk_version(&mut self, new_version: &str) -> Result<(), Error>;
+    fn rpaths(&self) -> Result<Vec<String>, Error>;
+    fn add_rpath(&mut self, path: &str) -> Result<(), Error>;
+    fn remove_rpath(&mut self, path: &str) -> Result<(), Error>;
+    fn install_name(&self) -> Result<Option<String>, Error>;
+    fn set_install_name(&mut self, name: &str) -> Result<(), Error>;
+    fn encryption_info(&self) -> Result<Option<EncryptionInfo>, Error>;
+    fn build_version(&self) -> Result<Option<BuildVersion>, Error>;
+    fn set_minimum_os_version(&mut self, new_version: &str) -> Result<(), Error>;
+    fn set_platform(&mut self, platform: u32) -> Result<(), Error>;
+    fn signature_info(&self) -> Result<Option<SignatureInfo>, Error>;
+    fn verify_signature(
+        &self,
+        info_plist: Option<&[u8]>,
+        code_resources: Option<&[u8]>,
+    ) -> Result<Option<Vec<SignatureMismatch>>, Error>;
+    fn remove_code_signature(&mut self) -> Result<(), Error>;
 }
 
 // theres multiple binaries in MachFile, being Vec<MachOBinary>
@@ -136,14 +362,6 @@ impl<'a> MachOExt for MachOBinary<'a> {
     }
 
     fn dylib_load_paths(&self) -> Result<Vec<String>, Error> {
-        const DYLIB_COMMANDS: &[u32] = &[
-            LC_LOAD_DYLIB,
-            LC_LOAD_WEAK_DYLIB,
-            LC_REEXPORT_DYLIB,
-            LC_LAZY_LOAD_DYLIB,
-            LC_LOAD_UPWARD_DYLIB,
-        ];
-
         let mut paths = Vec::new();
 
         for load_cmd in &self.macho.load_commands {
@@ -163,208 +381,50 @@ impl<'a> MachOExt for MachOBinary<'a> {
         Ok(paths)
     }
 
+    /// `LC_LOAD_WEAK_DYLIB` paths only, dyld skips these when they are missing.
+    fn weak_dylib_load_paths(&self) -> Result<Vec<String>, Error> {
+        Ok(self
+            .macho
+            .load_commands
+            .iter()
+            .filter(|load_cmd| load_cmd.command.cmd() == LC_LOAD_WEAK_DYLIB)
+            .filter_map(|load_cmd| manually_parse_dylib(self.data, load_cmd.offset))
+            .collect())
+    }
+
     // these require rewriting the Mach-O
     fn add_dylib_load_path(&mut self, path: &str) -> Result<(), Error> {
-        let macho = &self.macho;
-
-        let read_u32_le = |data: &[u8], offset: usize| -> u32 {
-            u32::from_le_bytes([
-                data[offset],
-                data[offset + 1],
-                data[offset + 2],
-                data[offset + 3],
-            ])
-        };
-
-        let dylib_exists_in_macho = |macho: &GoblinMachO, base_offset: usize| -> bool {
-            macho.load_commands.iter().any(|load_cmd| {
-                if let CommandVariant::LoadDylib(dylib) = &load_cmd.command {
-                    extract_dylib_path(&self.data, base_offset + load_cmd.offset, dylib.dylib.name)
-                        .map_or(false, |name| name == path)
-                } else {
-                    manually_parse_dylib(&self.data, base_offset + load_cmd.offset)
-                        .map_or(false, |name| name == path)
-                }
-            })
-        };
-
-        let is_64 = matches!(macho.header.cputype, CPU_TYPE_ARM64);
-        let dylib_exists = dylib_exists_in_macho(macho, 0);
-        let current_sizeofcmds = read_u32_le(&self.data, 20);
-        let current_ncmds = read_u32_le(&self.data, 16);
-
-        // Create a mutable Vec<u8> so we can modify the Mach-O data in place
-        let mut data = self.data.to_vec();
-
-        // with macho.data we can clone, modify, then set the data
-        // for our modified binary, then using MachO struct we can make our universal bin
-
-        if dylib_exists {
+        if self.dylib_load_paths()?.iter().any(|p| p == path) {
             log::warn!("Dylib already exists in binary: {}", path);
             return Ok(());
         }
 
-        let header_size = if is_64 { 32 } else { 28 };
-
-        // Calculate new load command size (must be 8-byte aligned)
-        let dylib_path_len = path.len();
-        let padding = (8 - ((dylib_path_len + 1) % 8)) % 8; // +1 for null terminator
-        let dylib_command_size = 24 + dylib_path_len + 1 + padding; // sizeof(dylib_command) = 24
-
-        // Find the position to insert the new load command
-        let header_offset = 0;
-        let load_commands_offset = header_offset + header_size;
-        let sizeofcmds_offset = header_offset + 20;
-        let ncmds_offset = header_offset + 16;
-
-        // Find the minimum non-zero file offset from segments
-        let min_fileoff = macho
-            .load_commands
-            .iter()
-            .filter_map(|load_cmd| match &load_cmd.command {
-                CommandVariant::Segment64(seg) if seg.filesize > 0 && seg.fileoff > 0 => {
-                    Some(seg.fileoff)
-                }
-                CommandVariant::Segment32(seg) if seg.filesize > 0 && seg.fileoff > 0 => {
-                    Some(seg.fileoff as u64)
-                }
-                _ => None,
-            })
-            .min()
-            .unwrap_or(u64::MAX);
-
-        // Calculate available space
-        let load_commands_end = load_commands_offset + current_sizeofcmds as usize;
-        let data_start = if min_fileoff < u64::MAX {
-            min_fileoff as usize
-        } else {
-            data.len()
-        };
-
-        let available_space = data_start.saturating_sub(load_commands_end);
-
-        if dylib_command_size > available_space {
-            return Err(Error::Parse);
-        }
-
-        // Write the new load command into the available space (no splice needed!)
-        let insert_offset = load_commands_end;
-        let mut new_command = Vec::new();
-        new_command.extend_from_slice(&(LC_LOAD_WEAK_DYLIB as u32).to_le_bytes()); // cmd
-        new_command.extend_from_slice(&(dylib_command_size as u32).to_le_bytes()); // cmdsize
-
-        // dylib_command structure:
-        // struct dylib {
-        //     uint32_t name;          // offset from start of load command to start of name string
-        //     uint32_t timestamp;     // date/time stamp
-        //     uint32_t current_version;
-        //     uint32_t compatibility_version;
-        // };
-        new_command.extend_from_slice(&24u32.to_le_bytes()); // name.offset (sizeof dylib_command header = 8 + 16 = 24)
-        new_command.extend_from_slice(&2u32.to_le_bytes()); // timestamp
-        new_command.extend_from_slice(&0x00010000u32.to_le_bytes()); // current_version (1.0.0)
-        new_command.extend_from_slice(&0x00010000u32.to_le_bytes()); // compatibility_version (1.0.0)
-        new_command.extend_from_slice(path.as_bytes());
-        new_command.push(0); // null terminator
-        new_command.extend(vec![0u8; padding]); // padding
-
-        // Write directly into the existing padding space
-        data[insert_offset..insert_offset + dylib_command_size].copy_from_slice(&new_command);
-
-        // Update header fields
-        let new_sizeofcmds = current_sizeofcmds + dylib_command_size as u32;
-        let new_ncmds = current_ncmds + 1;
-
-        data[sizeofcmds_offset..sizeofcmds_offset + 4]
-            .copy_from_slice(&new_sizeofcmds.to_le_bytes());
-        data[ncmds_offset..ncmds_offset + 4].copy_from_slice(&new_ncmds.to_le_bytes());
-
-        self.data = Box::leak(data.into_boxed_slice());
+        // timestamp 2, current and compatibility version 1.0.0
+        let command = build_dylib_command(LC_LOAD_WEAK_DYLIB, path, 2, 0x00010000, 0x00010000);
+        ensure_load_command_space(self, command.len())?;
 
-        Ok(())
+        let mut data = self.data.to_vec();
+        insert_load_command(&self.macho, &mut data, &command)?;
+        commit_data(self, data)
     }
 
     fn remove_dylib_load_path(&mut self, path: &str) -> Result<(), Error> {
-        let macho = &self.macho;
-        let mut data = self.data.to_vec();
-
-        let read_u32_le = |data: &[u8], offset: usize| -> u32 {
-            u32::from_le_bytes([
-                data[offset],
-                data[offset + 1],
-                data[offset + 2],
-                data[offset + 3],
-            ])
-        };
+        let matches = find_load_commands(self, DYLIB_COMMANDS, path);
 
-        let replacements: Vec<(usize, usize, usize)> = macho
-            .load_commands
-            .iter()
-            .filter_map(|load_cmd| {
-                match &load_cmd.command {
-                    CommandVariant::LoadDylib(dylib) => {
-                        extract_dylib_path(self.data, load_cmd.offset, dylib.dylib.name)
-                    }
-                    _ => manually_parse_dylib(self.data, load_cmd.offset),
-                }
-                .and_then(|name| {
-                    if name == path {
-                        let cmdsize = read_u32_le(&self.data, load_cmd.offset + 4) as usize;
-                        Some((0, load_cmd.offset, cmdsize))
-                    } else {
-                        None
-                    }
-                })
-            })
-            .collect();
-
-        if replacements.is_empty() {
+        if matches.is_empty() {
             log::warn!("No matching dylib load commands found for path: {}", path);
             return Ok(());
         }
 
-        let current_sizeofcmds = read_u32_le(&self.data, 20);
-        let current_ncmds = read_u32_le(&self.data, 16);
-        let mut total_removed_size = 0;
-        for (arch_offset, cmd_offset, cmdsize) in &replacements {
-            let absolute_cmd_offset = arch_offset + cmd_offset - total_removed_size;
-            // Shift subsequent data to overwrite the removed command
-            data.copy_within(absolute_cmd_offset + cmdsize.., absolute_cmd_offset);
-            total_removed_size += cmdsize;
-        }
-        // Update header fields
-        let new_sizeofcmds = current_sizeofcmds - total_removed_size as u32;
-        let new_ncmds = current_ncmds - replacements.len() as u32;
-        data[20..24].copy_from_slice(&new_sizeofcmds.to_le_bytes());
-        data[16..20].copy_from_slice(&new_ncmds.to_le_bytes());
-        data.truncate(20 + new_sizeofcmds as usize);
-
-        self.data = Box::leak(data.into_boxed_slice());
-
-        Ok(())
+        let mut data = self.data.to_vec();
+        remove_load_commands(&self.macho, &mut data, &matches)?;
+        commit_data(self, data)
     }
 
     fn replace_dylib_load_path(&mut self, old_path: &str, new_path: &str) -> Result<(), Error> {
         let macho = &self.macho;
         let mut data = self.data.to_vec();
 
-        const DYLIB_COMMANDS: &[u32] = &[
-            LC_LOAD_DYLIB,
-            LC_LOAD_WEAK_DYLIB,
-            LC_REEXPORT_DYLIB,
-            LC_LAZY_LOAD_DYLIB,
-            LC_LOAD_UPWARD_DYLIB,
-        ];
-
-        let read_u32_le = |data: &[u8], offset: usize| -> u32 {
-            u32::from_le_bytes([
-                data[offset],
-                data[offset + 1],
-                data[offset + 2],
-                data[offset + 3],
-            ])
-        };
-
         let find_dylib_matches = |macho: &GoblinMachO, base_offset: usize| -> Vec<(usize, usize)> {
             macho
                 .load_commands
@@ -439,35 +499,890 @@ impl<'a> MachOExt for MachOBinary<'a> {
     }
 
     fn replace_sdk_version(&mut self, new_version: &str) -> Result<(), Error> {
-        let macho = &self.macho;
+        // sdk sits at +16 in LC_BUILD_VERSION, +12 in LC_VERSION_MIN_*
+        patch_version_commands(self, 16, 12, encode_version(new_version)?)
+    }
+
+    fn rpaths(&self) -> Result<Vec<String>, Error> {
+        Ok(self
+            .macho
+            .load_commands
+            .iter()
+            .filter(|load_cmd| load_cmd.command.cmd() == LC_RPATH)
+            .filter_map(|load_cmd| manually_parse_dylib(self.data, load_cmd.offset))
+            .collect())
+    }
+
+    fn add_rpath(&mut self, path: &str) -> Result<(), Error> {
+        if self.rpaths()?.iter().any(|p| p == path) {
+            // most binaries already search Frameworks, nothing to warn about
+            log::debug!("Rpath already exists in binary: {}", path);
+            return Ok(());
+        }
+
+        let command = build_rpath_command(path);
+        ensure_load_command_space(self, command.len())?;
+
+        let mut data = self.data.to_vec();
+        insert_load_command(&self.macho, &mut data, &command)?;
+        commit_data(self, data)
+    }
+
+    fn remove_rpath(&mut self, path: &str) -> Result<(), Error> {
+        let matches = find_load_commands(self, &[LC_RPATH], path);
+
+        if matches.is_empty() {
+            log::warn!("No matching rpath load commands found for path: {}", path);
+            return Ok(());
+        }
+
+        let mut data = self.data.to_vec();
+        remove_load_commands(&self.macho, &mut data, &matches)?;
+        commit_data(self, data)
+    }
+
+    fn install_name(&self) -> Result<Option<String>, Error> {
+        Ok(self
+            .macho
+            .load_commands
+            .iter()
+            .find(|load_cmd| load_cmd.command.cmd() == LC_ID_DYLIB)
+            .and_then(|load_cmd| manually_parse_dylib(self.data, load_cmd.offset)))
+    }
+
+    fn set_install_name(&mut self, name: &str) -> Result<(), Error> {
+        let find_id_dylib = |binary: &MachOBinary<'_>| {
+            binary
+                .macho
+                .load_commands
+                .iter()
+                .find(|load_cmd| load_cmd.command.cmd() == LC_ID_DYLIB)
+                .map(|load_cmd| {
+                    (
+                        load_cmd.offset,
+                        read_u32_le(binary.data, load_cmd.offset + 4) as usize,
+                    )
+                })
+        };
+
+        let Some((offset, cmdsize)) = find_id_dylib(self) else {
+            log::warn!("Binary has no LC_ID_DYLIB, not a dylib?");
+            return Ok(());
+        };
+
+        // keep the timestamp and versions of the original identity
+        let command = build_dylib_command(
+            LC_ID_DYLIB,
+            name,
+            read_u32_le(self.data, offset + 12),
+            read_u32_le(self.data, offset + 16),
+            read_u32_le(self.data, offset + 20),
+        );
+        ensure_load_command_space(self, command.len().saturating_sub(cmdsize))?;
+
+        // making room may have moved the command around
+        let (offset, cmdsize) = find_id_dylib(self).ok_or(Error::Parse)?;
+
+        let mut data = self.data.to_vec();
+        splice_load_command(&self.macho, &mut data, offset, cmdsize, &command)?;
+        commit_data(self, data)
+    }
+
+    fn encryption_info(&self) -> Result<Option<EncryptionInfo>, Error> {
+        Ok(self
+            .macho
+            .load_commands
+            .iter()
+            .find_map(|load_cmd| match &load_cmd.command {
+                CommandVariant::EncryptionInfo32(info) => Some(EncryptionInfo {
+                    cryptoff: info.cryptoff,
+                    cryptsize: info.cryptsize,
+                    cryptid: info.cryptid,
+                }),
+                CommandVariant::EncryptionInfo64(info) => Some(EncryptionInfo {
+                    cryptoff: info.cryptoff,
+                    cryptsize: info.cryptsize,
+                    cryptid: info.cryptid,
+                }),
+                _ => None,
+            }))
+    }
+
+    fn build_version(&self) -> Result<Option<BuildVersion>, Error> {
+        let mut legacy = None;
+
+        for load_cmd in &self.macho.load_commands {
+            let offset = load_cmd.offset;
+            let cmd = load_cmd.command.cmd();
+
+            if cmd == LC_BUILD_VERSION {
+                return Ok(Some(BuildVersion {
+                    platform: read_u32_le(self.data, offset + 8),
+                    minos: format_version(read_u32_le(self.data, offset + 12)),
+                    sdk: format_version(read_u32_le(self.data, offset + 16)),
+                    legacy: false,
+                }));
+            }
+
+            if let (None, Some(platform)) = (&legacy, version_min_platform(cmd)) {
+                legacy = Some(BuildVersion {
+                    platform,
+                    minos: format_version(read_u32_le(self.data, offset + 8)),
+                    sdk: format_version(read_u32_le(self.data, offset + 12)),
+                    legacy: true,
+                });
+            }
+        }
+
+        Ok(legacy)
+    }
+
+    fn set_minimum_os_version(&mut self, new_version: &str) -> Result<(), Error> {
+        // minos sits at +12 in LC_BUILD_VERSION, +8 in LC_VERSION_MIN_*
+        patch_version_commands(self, 12, 8, encode_version(new_version)?)
+    }
+
+    fn set_platform(&mut self, platform: u32) -> Result<(), Error> {
+        let mut data = self.data.to_vec();
+
+        for load_cmd in &self.macho.load_commands {
+            let offset = load_cmd.offset;
+            let cmd = load_cmd.command.cmd();
+
+            if cmd == LC_BUILD_VERSION {
+                data[offset + 8..offset + 12].copy_from_slice(&platform.to_le_bytes());
+            } else if version_min_platform(cmd).is_some() {
+                // legacy commands encode the platform in the command itself
+                let new_cmd =
+                    version_min_command(platform).ok_or(Error::UnsupportedPlatform(platform))?;
+                data[offset..offset + 4].copy_from_slice(&new_cmd.to_le_bytes());
+            }
+        }
+
+        commit_data(self, data)
+    }
+
+    fn signature_info(&self) -> Result<Option<SignatureInfo>, Error> {
+        let Some(embedded_sig) = self.code_signature()? else {
+            return Ok(None);
+        };
+        let Some(code_directory) = embedded_sig.code_directory()? else {
+            return Ok(None);
+        };
+
+        let mut cdhashes = Vec::new();
+        let alternates = embedded_sig.alternate_code_directories()?;
+        for cd in std::iter::once(&code_directory).chain(alternates.iter().map(|(_, cd)| cd)) {
+            let digest = cd.digest_with(cd.digest_type)?;
+            // the kernel only ever looks at the first 20 bytes
+            let cdhash = hex::encode(&digest[..digest.len().min(20)]);
+            cdhashes.push((cd.digest_type.to_string(), cdhash));
+        }
+
+        let mut requirements = Vec::new();
+        if let Some(requirement_set) = embedded_sig.code_requirements()? {
+            for (requirement_type, blob) in &requirement_set.requirements {
+                for expression in blob.parse_expressions()?.iter() {
+                    requirements.push(format!("{requirement_type} => {expression}"));
+                }
+            }
+            requirements.sort();
+        }
+
+        // Apple embeds the whole chain, the leaf is the one that issued nothing else
+        let certificate_subject = match embedded_sig.signed_data()? {
+            Some(signed_data) => {
+                let certificates: Vec<_> = signed_data.certificates().collect();
+                certificates
+                    .iter()
+                    .copied()
+                    .find(|cert| {
+                        !certificates.iter().any(|other| {
+                            other.issuer_name() == cert.subject_name()
+                                && !std::ptr::eq(*other, *cert)
+                        })
+                    })
+                    .and_then(|cert| cert.subject_common_name())
+            }
+            None => None,
+        };
+
+        Ok(Some(SignatureInfo {
+            identifier: code_directory.ident.to_string(),
+            team_id: code_directory.team_name.as_ref().map(|t| t.to_string()),
+            cdhashes,
+            certificate_subject,
+            requirements,
+            entitlements: self.embedded_entitlements()?,
+        }))
+    }
+
+    /// Recomputes every hash in every code directory. `info_plist` and `code_resources`
+    /// are the bundle files the signature seals, `None` when there is no such file.
+    /// Returns `None` for unsigned slices.
+    fn verify_signature(
+        &self,
+        info_plist: Option<&[u8]>,
+        code_resources: Option<&[u8]>,
+    ) -> Result<Option<Vec<SignatureMismatch>>, Error> {
+        let Some(embedded_sig) = self.code_signature()? else {
+            return Ok(None);
+        };
+        let Some(code_directory) = embedded_sig.code_directory()? else {
+            return Ok(None);
+        };
+
+        let mut mismatches = Vec::new();
+        let mut push = |mismatch| {
+            if !mismatches.contains(&mismatch) {
+                mismatches.push(mismatch);
+            }
+        };
+
+        let alternates = embedded_sig.alternate_code_directories()?;
+        for cd in std::iter::once(&code_directory).chain(alternates.iter().map(|(_, cd)| cd)) {
+            // a page size of 0 means the whole binary is a single page
+            let code_limit = (cd.code_limit as usize).min(self.data.len());
+            let page_size = match cd.page_size as usize {
+                0 => code_limit.max(1),
+                size => size,
+            };
+
+            let pages: Vec<&[u8]> = self.data[..code_limit].chunks(page_size).collect();
+            if pages.len() != cd.code_digests.len() {
+                push(SignatureMismatch::PageCount {
+                    expected: cd.code_digests.len(),
+                    actual: pages.len(),
+                });
+            }
+            for (index, (page, expected)) in pages.iter().zip(&cd.code_digests).enumerate() {
+                if cd.digest_type.digest_data(page)? != *expected.data {
+                    push(SignatureMismatch::CodePage(index));
+                }
+            }
+
+            let blob_digest = |slot| {
+                embedded_sig
+                    .find_slot(slot)
+                    .map(|entry| entry.digest_with(cd.digest_type))
+                    .transpose()
+            };
+            let slots = [
+                (
+                    CodeSigningSlot::Info,
+                    "Info.plist",
+                    info_plist
+                        .map(|data| cd.digest_type.digest_data(data))
+                        .transpose()?,
+                ),
+                (
+                    CodeSigningSlot::ResourceDir,
+                    "CodeResources",
+                    code_resources
+                        .map(|data| cd.digest_type.digest_data(data))
+                        .transpose()?,
+                ),
+                (
+                    CodeSigningSlot::RequirementSet,
+                    "requirements",
+                    blob_digest(CodeSigningSlot::RequirementSet)?,
+                ),
+                (
+                    CodeSigningSlot::Entitlements,
+                    "entitlements",
+                    blob_digest(CodeSigningSlot::Entitlements)?,
+                ),
+                (
+                    CodeSigningSlot::EntitlementsDer,
+                    "DER entitlements",
+                    blob_digest(CodeSigningSlot::EntitlementsDer)?,
+                ),
+            ];
+
+            for (slot, name, actual) in slots {
+                let name = name.to_string();
+                match (cd.special_digests.get(&slot), actual) {
+                    (Some(expected), Some(actual)) if *expected.data != actual[..] => {
+                        push(SignatureMismatch::Slot(name))
+                    }
+                    (Some(_), None) => push(SignatureMismatch::MissingSlot(name)),
+                    (None, Some(_)) => push(SignatureMismatch::UnsealedSlot(name)),
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(Some(mismatches))
+    }
+
+    fn remove_code_signature(&mut self) -> Result<(), Error> {
+        let Some(load_cmd) = self
+            .macho
+            .load_commands
+            .iter()
+            .find(|load_cmd| load_cmd.command.cmd() == LC_CODE_SIGNATURE)
+        else {
+            return Ok(());
+        };
+
+        let offset = load_cmd.offset;
+        let cmdsize = read_u32_le(self.data, offset + 4) as usize;
+        let dataoff = read_u32_le(self.data, offset + 8) as usize;
+        let datasize = read_u32_le(self.data, offset + 12) as usize;
+
         let mut data = self.data.to_vec();
 
-        let version_parts: Vec<&str> = new_version.split('.').collect();
-        if version_parts.len() != 3 {
+        // the signature is the tail of __LINKEDIT, drop it and shrink the segment to match
+        if dataoff <= data.len() && dataoff + datasize >= data.len() {
+            shrink_linkedit(&self.macho, &mut data, dataoff);
+            data.truncate(dataoff);
+        }
+
+        remove_load_commands(&self.macho, &mut data, &[(offset, cmdsize)])?;
+        commit_data(self, data)
+    }
+}
+
+/// Sets `__LINKEDIT`'s filesize so the segment ends at `end`, and its vmsize to
+/// match, rounded up like the signer does.
+fn shrink_linkedit(macho: &GoblinMachO, data: &mut [u8], end: usize) {
+    for load_cmd in &macho.load_commands {
+        let offset = load_cmd.offset;
+
+        match &load_cmd.command {
+            CommandVariant::Segment64(segment) if segment.segname == LINKEDIT_SEGNAME => {
+                let filesize = (end as u64).saturating_sub(segment.fileoff);
+                let vmsize = filesize.next_multiple_of(LINKEDIT_VMSIZE_ALIGNMENT);
+                data[offset + 32..offset + 40].copy_from_slice(&vmsize.to_le_bytes());
+                data[offset + 48..offset + 56].copy_from_slice(&filesize.to_le_bytes());
+            }
+            CommandVariant::Segment32(segment) if segment.segname == LINKEDIT_SEGNAME => {
+                let filesize = (end as u32).saturating_sub(segment.fileoff);
+                let vmsize = filesize.next_multiple_of(LINKEDIT_VMSIZE_ALIGNMENT as u32);
+                data[offset + 28..offset + 32].copy_from_slice(&vmsize.to_le_bytes());
+                data[offset + 36..offset + 40].copy_from_slice(&filesize.to_le_bytes());
+            }
+            _ => {}
+        }
+    }
+}
+
+fn read_u32_le(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+/// Encodes `major[.minor[.patch]]` as a Mach-O version (`xxxx.yy.zz` nibbles).
+fn encode_version(version: &str) -> Result<u32, Error> {
+    let parts = version
+        .split('.')
+        .map(|p| p.parse::<u32>().map_err(|_| Error::Parse))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let (major, minor, patch) = match parts[..] {
+        [major] => (major, 0, 0),
+        [major, minor] => (major, minor, 0),
+        [major, minor, patch] => (major, minor, patch),
+        _ => return Err(Error::Parse),
+    };
+
+    if major > 0xffff || minor > 0xff || patch > 0xff {
+        return Err(Error::Parse);
+    }
+
+    Ok((major << 16) | (minor << 8) | patch)
+}
+
+fn format_version(version: u32) -> String {
+    let (major, minor, patch) = (version >> 16, (version >> 8) & 0xff, version & 0xff);
+    if patch == 0 {
+        format!("{major}.{minor}")
+    } else {
+        format!("{major}.{minor}.{patch}")
+    }
+}
+
+fn version_min_platform(cmd: u32) -> Option<u32> {
+    match cmd {
+        LC_VERSION_MIN_MACOSX => Some(PLATFORM_MACOS),
+        LC_VERSION_MIN_IPHONEOS => Some(PLATFORM_IOS),
+        LC_VERSION_MIN_TVOS => Some(PLATFORM_TVOS),
+        LC_VERSION_MIN_WATCHOS => Some(PLATFORM_WATCHOS),
+        _ => None,
+    }
+}
+
+fn version_min_command(platform: u32) -> Option<u32> {
+    match platform {
+        PLATFORM_MACOS => Some(LC_VERSION_MIN_MACOSX),
+        PLATFORM_IOS => Some(LC_VERSION_MIN_IPHONEOS),
+        PLATFORM_TVOS => Some(LC_VERSION_MIN_TVOS),
+        PLATFORM_WATCHOS => Some(LC_VERSION_MIN_WATCHOS),
+        _ => None,
+    }
+}
+
+/// Writes an encoded version into every `LC_BUILD_VERSION` and `LC_VERSION_MIN_*`
+/// command, at the given field offsets.
+fn patch_version_commands(
+    binary: &mut MachOBinary<'_>,
+    build_version_field: usize,
+    version_min_field: usize,
+    version: u32,
+) -> Result<(), Error> {
+    let mut data = binary.data.to_vec();
+
+    for load_cmd in &binary.macho.load_commands {
+        let cmd = load_cmd.command.cmd();
+        let field = if cmd == LC_BUILD_VERSION {
+            build_version_field
+        } else if version_min_platform(cmd).is_some() {
+            version_min_field
+        } else {
+            continue;
+        };
+
+        let offset = load_cmd.offset + field;
+        if offset + 4 > data.len() {
             return Err(Error::Parse);
         }
-        let major: u32 = version_parts[0].parse().map_err(|_| Error::Parse)?;
-        let minor: u32 = version_parts[1].parse().map_err(|_| Error::Parse)?;
-        let patch: u32 = version_parts[2].parse().map_err(|_| Error::Parse)?;
-        let new_version_encoded = (major << 16) | (minor << 8) | patch;
 
-        for load_cmd in &macho.load_commands {
-            if load_cmd.command.cmd() == goblin::mach::load_command::LC_BUILD_VERSION {
-                let sdk_offset = load_cmd.offset + 16;
+        data[offset..offset + 4].copy_from_slice(&version.to_le_bytes());
+    }
+
+    commit_data(binary, data)
+}
+
+/// Replaces the binary's data with an edited copy and re-parses its load commands,
+/// so further edits on the same binary see up-to-date offsets.
+fn commit_data(binary: &mut MachOBinary<'_>, data: Vec<u8>) -> Result<(), Error> {
+    let data: &'static [u8] = Box::leak(data.into_boxed_slice());
+    binary.macho = GoblinMachO::parse(data, 0).map_err(|_| Error::Parse)?;
+    binary.data = data;
+
+    Ok(())
+}
+
+/// Returns `(offset, cmdsize)` of every load command of the given types whose
+/// string argument (dylib name, rpath) equals `path`.
+fn find_load_commands(binary: &MachOBinary<'_>, cmds: &[u32], path: &str) -> Vec<(usize, usize)> {
+    binary
+        .macho
+        .load_commands
+        .iter()
+        .filter(|load_cmd| cmds.contains(&load_cmd.command.cmd()))
+        .filter(|load_cmd| {
+            manually_parse_dylib(binary.data, load_cmd.offset).is_some_and(|p| p == path)
+        })
+        .map(|load_cmd| {
+            (
+                load_cmd.offset,
+                read_u32_le(binary.data, load_cmd.offset + 4) as usize,
+            )
+        })
+        .collect()
+}
+
+/// Size of a load command carrying a string at `header_size`, padded to 8 bytes.
+fn padded_command_size(header_size: usize, string: &str) -> usize {
+    (header_size + string.len() + 1 + 7) & !7
+}
+
+/// Builds a `dylib_command` (`LC_LOAD_DYLIB`, `LC_ID_DYLIB`, ...).
+fn build_dylib_command(
+    cmd: u32,
+    path: &str,
+    timestamp: u32,
+    current_version: u32,
+    compatibility_version: u32,
+) -> Vec<u8> {
+    // struct dylib_command {
+    //     uint32_t cmd;
+    //     uint32_t cmdsize;
+    //     struct dylib {
+    //         uint32_t name;          // offset from start of load command to start of name string
+    //         uint32_t timestamp;
+    //         uint32_t current_version;
+    //         uint32_t compatibility_version;
+    //     };
+    // };
+    let size = padded_command_size(24, path);
+    let mut command = Vec::with_capacity(size);
+    command.extend_from_slice(&cmd.to_le_bytes());
+    command.extend_from_slice(&(size as u32).to_le_bytes());
+    command.extend_from_slice(&24u32.to_le_bytes());
+    command.extend_from_slice(&timestamp.to_le_bytes());
+    command.extend_from_slice(&current_version.to_le_bytes());
+    command.extend_from_slice(&compatibility_version.to_le_bytes());
+    command.extend_from_slice(path.as_bytes());
+    command.resize(size, 0); // null terminator and padding
+
+    command
+}
+
+/// Builds an `rpath_command` (`LC_RPATH`).
+fn build_rpath_command(path: &str) -> Vec<u8> {
+    // struct rpath_command {
+    //     uint32_t cmd;
+    //     uint32_t cmdsize;
+    //     uint32_t path;              // offset from start of load command to start of path string
+    // };
+    let size = padded_command_size(12, path);
+    let mut command = Vec::with_capacity(size);
+    command.extend_from_slice(&LC_RPATH.to_le_bytes());
+    command.extend_from_slice(&(size as u32).to_le_bytes());
+    command.extend_from_slice(&12u32.to_le_bytes());
+    command.extend_from_slice(path.as_bytes());
+    command.resize(size, 0); // null terminator and padding
+
+    command
+}
+
+/// Returns the end of the load commands and the file offset of the first
+/// section or segment data, which bounds how far the load commands may grow.
+fn load_command_bounds(macho: &GoblinMachO, data: &[u8]) -> (usize, usize) {
+    let header_size = if macho.is_64 { 32 } else { 28 };
+    let load_commands_end = header_size + read_u32_le(data, 20) as usize;
+
+    // __TEXT starts at file offset 0, so its first section is what actually
+    // follows the header padding
+    let section_start = macho
+        .segments
+        .iter()
+        .filter_map(|seg| seg.sections().ok())
+        .flatten()
+        .filter(|(section, _)| section.offset > 0)
+        .map(|(section, _)| section.offset as usize);
+
+    // Find the minimum non-zero file offset from segments
+    let data_start = macho
+        .load_commands
+        .iter()
+        .filter_map(|load_cmd| match &load_cmd.command {
+            CommandVariant::Segment64(seg) if seg.filesize > 0 && seg.fileoff > 0 => {
+                Some(seg.fileoff as usize)
+            }
+            CommandVariant::Segment32(seg) if seg.filesize > 0 && seg.fileoff > 0 => {
+                Some(seg.fileoff as usize)
+            }
+            _ => None,
+        })
+        .chain(section_start)
+        .min()
+        .unwrap_or(data.len());
+
+    (load_commands_end, data_start)
+}
+
+fn free_load_command_space(macho: &GoblinMachO, data: &[u8]) -> usize {
+    let (load_commands_end, data_start) = load_command_bounds(macho, data);
+    data_start.saturating_sub(load_commands_end)
+}
+
+/// Makes sure `size` more bytes of load commands fit before the first section.
+///
+/// When the header padding is exhausted, reclaims slack left inside string
+/// commands and then drops commands dyld doesn't use at runtime, along with
+/// their `__LINKEDIT` data. An existing `LC_CODE_SIGNATURE` is kept on purpose,
+/// the signer reuses it and would otherwise need the same 16 bytes back to add
+/// a new one.
+fn ensure_load_command_space(binary: &mut MachOBinary<'_>, size: usize) -> Result<(), Error> {
+    let required = |binary: &MachOBinary<'_>| {
+        let has_code_signature = binary
+            .macho
+            .load_commands
+            .iter()
+            .any(|load_cmd| load_cmd.command.cmd() == LC_CODE_SIGNATURE);
+
+        if has_code_signature {
+            size
+        } else {
+            size + CODE_SIGNATURE_COMMAND_SIZE
+        }
+    };
+
+    if free_load_command_space(&binary.macho, binary.data) >= required(binary) {
+        return Ok(());
+    }
+
+    let mut data = binary.data.to_vec();
+    compact_load_commands(&binary.macho, &mut data)?;
+    commit_data(binary, data)?;
+
+    if free_load_command_space(&binary.macho, binary.data) >= required(binary) {
+        return Ok(());
+    }
+
+    let linkedit = movable_linkedit(&binary.macho, binary.data);
+    let mut disposable = Vec::new();
+    let mut payloads = Vec::new();
+
+    for load_cmd in &binary.macho.load_commands {
+        if !DISPOSABLE_COMMANDS.contains(&load_cmd.command.cmd()) {
+            continue;
+        }
+
+        // keep the command rather than leave its data behind unreferenced
+        let payload = linkedit_payload(&load_cmd.command);
+        let cuttable = payload.is_none_or(|(dataoff, datasize)| {
+            linkedit
+                .as_ref()
+                .is_some_and(|range| range.start <= dataoff && dataoff + datasize <= range.end)
+        });
+        if !cuttable {
+            continue;
+        }
+
+        disposable.push((
+            load_cmd.offset,
+            read_u32_le(binary.data, load_cmd.offset + 4) as usize,
+        ));
+        payloads.extend(payload);
+    }
+
+    if !disposable.is_empty() {
+        log::warn!(
+            "Header padding exhausted, dropping {} unused load command(s)",
+            disposable.len()
+        );
+        let mut data = binary.data.to_vec();
+
+        // last first, cutting data only moves what comes after it
+        payloads.sort_by_key(|(dataoff, _)| std::cmp::Reverse(*dataoff));
+        for (dataoff, datasize) in payloads {
+            remove_linkedit_data(&binary.macho, &mut data, dataoff, datasize)?;
+        }
+
+        remove_load_commands(&binary.macho, &mut data, &disposable)?;
+        commit_data(binary, data)?;
+    }
+
+    let required = required(binary);
+    let available = free_load_command_space(&binary.macho, binary.data);
+    if available < required {
+        return Err(Error::MachOLoadCommandSpace {
+            required,
+            available,
+        });
+    }
+
+    Ok(())
+}
+
+/// `(dataoff, datasize)` of the `__LINKEDIT` data a disposable command points at.
+fn linkedit_payload(command: &CommandVariant) -> Option<(usize, usize)> {
+    match command {
+        CommandVariant::DylibCodeSignDrs(command)
+        | CommandVariant::LinkerOptimizationHint(command)
+            if command.datasize > 0 =>
+        {
+            Some((command.dataoff as usize, command.datasize as usize))
+        }
+        _ => None,
+    }
+}
+
+/// Offsets of the fields in a load command that hold file offsets into `__LINKEDIT`.
+fn linkedit_offset_fields(command: &CommandVariant) -> &'static [usize] {
+    match command {
+        // symoff, stroff
+        CommandVariant::Symtab(_) => &[8, 16],
+        // tocoff, modtaboff, extrefsymoff, indirectsymoff, extreloff, locreloff
+        CommandVariant::Dysymtab(_) => &[32, 40, 48, 56, 64, 72],
+        // rebase_off, bind_off, weak_bind_off, lazy_bind_off, export_off
+        CommandVariant::DyldInfo(_) | CommandVariant::DyldInfoOnly(_) => &[8, 16, 24, 32, 40],
+        // dataoff
+        CommandVariant::CodeSignature(_)
+        | CommandVariant::SegmentSplitInfo(_)
+        | CommandVariant::FunctionStarts(_)
+        | CommandVariant::DataInCode(_)
+        | CommandVariant::DylibCodeSignDrs(_)
+        | CommandVariant::LinkerOptimizationHint(_)
+        | CommandVariant::DyldExportsTrie(_)
+        | CommandVariant::DyldChainedFixups(_) => &[8],
+        _ => &[],
+    }
+}
 
-                if sdk_offset + 4 > data.len() {
-                    return Err(Error::Parse);
+/// File range of `__LINKEDIT` if data can be cut out of it: the segment has to end
+/// the file and every command that can point into it has to be one we know how
+/// to update.
+fn movable_linkedit(macho: &GoblinMachO, data: &[u8]) -> Option<std::ops::Range<usize>> {
+    let unknown = macho.load_commands.iter().any(|load_cmd| {
+        matches!(
+            load_cmd.command,
+            CommandVariant::Symseg(_)
+                | CommandVariant::TwolevelHints(_)
+                | CommandVariant::Note(_)
+                | CommandVariant::Unimplemented(_)
+        )
+    });
+    if unknown {
+        return None;
+    }
+
+    let (fileoff, filesize) =
+        macho
+            .load_commands
+            .iter()
+            .find_map(|load_cmd| match &load_cmd.command {
+                CommandVariant::Segment64(segment) if segment.segname == LINKEDIT_SEGNAME => {
+                    Some((segment.fileoff as usize, segment.filesize as usize))
                 }
+                CommandVariant::Segment32(segment) if segment.segname == LINKEDIT_SEGNAME => {
+                    Some((segment.fileoff as usize, segment.filesize as usize))
+                }
+                _ => None,
+            })?;
+
+    (fileoff + filesize == data.len()).then_some(fileoff..data.len())
+}
 
-                data[sdk_offset..sdk_offset + 4]
-                    .copy_from_slice(&new_version_encoded.to_le_bytes());
+/// Cuts `size` bytes at `start` out of `__LINKEDIT`, moving the data after them
+/// down along with every offset that points at it. Only a multiple of 8 bytes is
+/// cut so the tables that follow stay aligned, any remainder is zeroed instead.
+fn remove_linkedit_data(
+    macho: &GoblinMachO,
+    data: &mut Vec<u8>,
+    start: usize,
+    size: usize,
+) -> Result<(), Error> {
+    let end = start
+        .checked_add(size)
+        .filter(|end| *end <= data.len())
+        .ok_or(Error::Parse)?;
+    let cut = size & !7;
+
+    for load_cmd in &macho.load_commands {
+        for field in linkedit_offset_fields(&load_cmd.command) {
+            let offset = load_cmd.offset + field;
+            let value = read_u32_le(data, offset) as usize;
+            if value >= end {
+                data[offset..offset + 4].copy_from_slice(&((value - cut) as u32).to_le_bytes());
             }
         }
+    }
 
-        self.data = Box::leak(data.into_boxed_slice());
+    data[start..end].fill(0);
+    data.drain(start..start + cut);
 
-        Ok(())
+    let new_end = data.len();
+    shrink_linkedit(macho, data, new_end);
+
+    Ok(())
+}
+
+/// Shrinks string commands (dylibs, rpaths) whose `cmdsize` is larger than their
+/// string needs, e.g. after a shorter path was written over a longer one.
+fn compact_load_commands(macho: &GoblinMachO, data: &mut [u8]) -> Result<(), Error> {
+    let mut compactable: Vec<(usize, usize, Vec<u8>)> = Vec::new();
+
+    for load_cmd in &macho.load_commands {
+        let cmd = load_cmd.command.cmd();
+        if !DYLIB_COMMANDS.contains(&cmd) && cmd != LC_ID_DYLIB && cmd != LC_RPATH {
+            continue;
+        }
+
+        let offset = load_cmd.offset;
+        let cmdsize = read_u32_le(data, offset + 4) as usize;
+        let name_offset = read_u32_le(data, offset + 8) as usize;
+        let Some(name) = manually_parse_dylib(data, offset) else {
+            continue;
+        };
+
+        let size = padded_command_size(name_offset, &name);
+        if name_offset >= cmdsize || size >= cmdsize {
+            continue;
+        }
+
+        let mut command = data[offset..offset + name_offset].to_vec();
+        command[4..8].copy_from_slice(&(size as u32).to_le_bytes());
+        command.extend_from_slice(name.as_bytes());
+        command.resize(size, 0);
+
+        compactable.push((offset, cmdsize, command));
+    }
+
+    // last first so earlier offsets stay valid
+    for (offset, cmdsize, command) in compactable.into_iter().rev() {
+        splice_load_command(macho, data, offset, cmdsize, &command)?;
     }
+
+    Ok(())
+}
+
+/// Replaces `old_size` bytes of load commands at `offset` with `new_command`,
+/// shifting the following load commands and updating `ncmds` and `sizeofcmds`.
+/// An `old_size` of zero inserts, an empty `new_command` removes.
+fn splice_load_command(
+    macho: &GoblinMachO,
+    data: &mut [u8],
+    offset: usize,
+    old_size: usize,
+    new_command: &[u8],
+) -> Result<(), Error> {
+    let (load_commands_end, data_start) = load_command_bounds(macho, data);
+    let new_end = load_commands_end - old_size + new_command.len();
+
+    if new_end > data_start {
+        return Err(Error::MachOLoadCommandSpace {
+            required: new_end - load_commands_end,
+            available: data_start.saturating_sub(load_commands_end),
+        });
+    }
+
+    data.copy_within(
+        offset + old_size..load_commands_end,
+        offset + new_command.len(),
+    );
+    data[offset..offset + new_command.len()].copy_from_slice(new_command);
+    if new_end < load_commands_end {
+        data[new_end..load_commands_end].fill(0);
+    }
+
+    let mut ncmds = read_u32_le(data, 16);
+    if old_size == 0 && !new_command.is_empty() {
+        ncmds += 1;
+    } else if old_size > 0 && new_command.is_empty() {
+        ncmds -= 1;
+    }
+    let sizeofcmds = read_u32_le(data, 20) as usize - old_size + new_command.len();
+
+    data[16..20].copy_from_slice(&ncmds.to_le_bytes());
+    data[20..24].copy_from_slice(&(sizeofcmds as u32).to_le_bytes());
+
+    Ok(())
+}
+
+/// Appends a load command after the existing ones.
+fn insert_load_command(
+    macho: &GoblinMachO,
+    data: &mut [u8],
+    new_command: &[u8],
+) -> Result<(), Error> {
+    let (load_commands_end, _) = load_command_bounds(macho, data);
+    splice_load_command(macho, data, load_commands_end, 0, new_command)
+}
+
+/// Removes the given `(offset, cmdsize)` load commands, last first so earlier
+/// offsets stay valid.
+fn remove_load_commands(
+    macho: &GoblinMachO,
+    data: &mut [u8],
+    commands: &[(usize, usize)],
+) -> Result<(), Error> {
+    let mut commands = commands.to_vec();
+    commands.sort_by_key(|(offset, _)| std::cmp::Reverse(*offset));
+
+    for (offset, cmdsize) in commands {
+        splice_load_command(macho, data, offset, cmdsize, &[])?;
+    }
+
+    Ok(())
 }
 
 fn extract_dylib_path(
@@ -491,6 +1406,7 @@ fn extract_dylib_path(
 }
 
 // TODO: our custom ones need manual parsing?
+// also used for LC_RPATH, whose path offset sits at the same place
 fn manually_parse_dylib(file_data: &[u8], load_cmd_offset: usize) -> Option<String> {
     if load_cmd_offset + 12 > file_data.len() {
         return None;
@@ -505,3 +1421,339 @@ fn manually_parse_dylib(file_data: &[u8], load_cmd_offset: usize) -> Option<Stri
 
     extract_dylib_path(file_data, load_cmd_offset, name_offset_field)
 }
+
+#[cfg(test)]
+mod tests {
+    use apple_codesign::MachOBinary;
+    use goblin::mach::load_command::{LC_SEGMENT_64, LC_SYMTAB, PLATFORM_MACCATALYST};
+
+    use super::*;
+
+    const CPU_TYPE_ARM64: u32 = 0x0100_000c;
+    const MH_EXECUTE: u32 = 2;
+    const TEXT_SIZE: usize = 16;
+
+    fn name16(name: &str) -> [u8; 16] {
+        let mut bytes = [0; 16];
+        bytes[..name.len()].copy_from_slice(name.as_bytes());
+        bytes
+    }
+
+    /// An `LC_SEGMENT_64`, with a single `__text` section at `section` if given.
+    fn segment(
+        name: &str,
+        vmaddr: u64,
+        fileoff: usize,
+        filesize: usize,
+        section: Option<usize>,
+    ) -> Vec<u8> {
+        let nsects = u32::from(section.is_some());
+        let mut command = Vec::new();
+        command.extend_from_slice(&LC_SEGMENT_64.to_le_bytes());
+        command.extend_from_slice(&(72 + 80 * nsects).to_le_bytes());
+        command.extend_from_slice(&name16(name));
+        command.extend_from_slice(&vmaddr.to_le_bytes());
+        command.extend_from_slice(&(filesize.next_multiple_of(0x4000) as u64).to_le_bytes());
+        command.extend_from_slice(&(fileoff as u64).to_le_bytes());
+        command.extend_from_slice(&(filesize as u64).to_le_bytes());
+        for value in [5, 5, nsects, 0] {
+            command.extend_from_slice(&value.to_le_bytes()); // maxprot, initprot, nsects, flags
+        }
+
+        if let Some(offset) = section {
+            command.extend_from_slice(&name16("__text"));
+            command.extend_from_slice(&name16(name));
+            command.extend_from_slice(&(vmaddr + offset as u64).to_le_bytes());
+            command.extend_from_slice(&(TEXT_SIZE as u64).to_le_bytes());
+            for value in [offset as u32, 2, 0, 0, 0x8000_0400, 0, 0, 0] {
+                command.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+
+        command
+    }
+
+    fn build_version(platform: u32, minos: u32, sdk: u32) -> Vec<u8> {
+        [LC_BUILD_VERSION, 24, platform, minos, sdk, 0]
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect()
+    }
+
+    fn linkedit_data(cmd: u32, dataoff: usize, datasize: usize) -> Vec<u8> {
+        [cmd, 16, dataoff as u32, datasize as u32]
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect()
+    }
+
+    fn symtab(symoff: usize, nsyms: u32, stroff: usize, strsize: u32) -> Vec<u8> {
+        [LC_SYMTAB, 24, symoff as u32, nsyms, stroff as u32, strsize]
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect()
+    }
+
+    /// An `LC_RPATH` of `cmdsize` bytes, which may leave slack after the path.
+    fn rpath(path: &str, cmdsize: usize) -> Vec<u8> {
+        let mut command = Vec::new();
+        for value in [LC_RPATH, cmdsize as u32, 12] {
+            command.extend_from_slice(&value.to_le_bytes());
+        }
+        command.extend_from_slice(path.as_bytes());
+        command.resize(cmdsize, 0);
+        command
+    }
+
+    /// `(vmsize, filesize)` of the segment named `name`.
+    fn segment_sizes(binary: &MachOBinary<'_>, name: &str) -> (u64, u64) {
+        binary
+            .macho
+            .load_commands
+            .iter()
+            .find_map(|load_cmd| match &load_cmd.command {
+                CommandVariant::Segment64(segment) if segment.segname == name16(name) => {
+                    Some((segment.vmsize, segment.filesize))
+                }
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    fn has_command(binary: &MachOBinary<'_>, cmd: u32) -> bool {
+        binary
+            .macho
+            .load_commands
+            .iter()
+            .any(|load_cmd| load_cmd.command.cmd() == cmd)
+    }
+
+    fn version_min(cmd: u32, version: u32, sdk: u32) -> Vec<u8> {
+        [cmd, 16, version, sdk]
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect()
+    }
+
+    /// An arm64 executable whose `__text` section starts `padding` bytes after the
+    /// load commands, followed by `__LINKEDIT` holding `linkedit`. `commands` gets
+    /// the file offset of `__LINKEDIT` and must return the same sizes for any offset.
+    fn build_binary(
+        padding: usize,
+        linkedit: &[u8],
+        commands: impl Fn(usize) -> Vec<Vec<u8>>,
+    ) -> Vec<u8> {
+        let sizeofcmds = 152 + 72 + commands(0).iter().map(Vec::len).sum::<usize>();
+        let text_offset = 32 + sizeofcmds + padding;
+        let linkedit_offset = text_offset + TEXT_SIZE;
+        let text_vmsize = linkedit_offset.next_multiple_of(0x4000) as u64;
+
+        let mut load_commands = vec![
+            segment(
+                "__TEXT",
+                0x1_0000_0000,
+                0,
+                linkedit_offset,
+                Some(text_offset),
+            ),
+            segment(
+                "__LINKEDIT",
+                0x1_0000_0000 + text_vmsize,
+                linkedit_offset,
+                linkedit.len(),
+                None,
+            ),
+        ];
+        load_commands.extend(commands(linkedit_offset));
+
+        let mut data = Vec::new();
+        for value in [
+            0xfeed_facf,
+            CPU_TYPE_ARM64,
+            0,
+            MH_EXECUTE,
+            load_commands.len() as u32,
+            sizeofcmds as u32,
+            0,
+            0,
+        ] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        for command in &load_commands {
+            data.extend_from_slice(command);
+        }
+        data.resize(linkedit_offset, 0);
+        data.extend_from_slice(linkedit);
+
+        data
+    }
+
+    #[test]
+    fn build_version_round_trips() {
+        let data = build_binary(64, &[0; 16], |_| {
+            vec![build_version(PLATFORM_IOS, 0x000e_0000, 0x0011_0200)]
+        });
+        let mut binary = MachOBinary::parse(&data).unwrap();
+
+        assert_eq!(
+            binary.build_version().unwrap(),
+            Some(BuildVersion {
+                platform: PLATFORM_IOS,
+                minos: "14.0".to_string(),
+                sdk: "17.2".to_string(),
+                legacy: false,
+            })
+        );
+
+        binary.set_minimum_os_version("7.0").unwrap();
+        binary.replace_sdk_version("26.0.1").unwrap();
+        binary.set_platform(PLATFORM_MACCATALYST).unwrap();
+
+        assert_eq!(
+            binary.build_version().unwrap(),
+            Some(BuildVersion {
+                platform: PLATFORM_MACCATALYST,
+                minos: "7.0".to_string(),
+                sdk: "26.0.1".to_string(),
+                legacy: false,
+            })
+        );
+    }
+
+    #[test]
+    fn version_min_round_trips() {
+        let data = build_binary(64, &[0; 16], |_| {
+            vec![version_min(
+                LC_VERSION_MIN_IPHONEOS,
+                0x0009_0000,
+                0x000c_0400,
+            )]
+        });
+        let mut binary = MachOBinary::parse(&data).unwrap();
+
+        assert_eq!(
+            binary.build_version().unwrap(),
+            Some(BuildVersion {
+                platform: PLATFORM_IOS,
+                minos: "9.0".to_string(),
+                sdk: "12.4".to_string(),
+                legacy: true,
+            })
+        );
+
+        binary.set_minimum_os_version("7.0.1").unwrap();
+        binary.replace_sdk_version("13.0").unwrap();
+        binary.set_platform(PLATFORM_TVOS).unwrap();
+
+        let expected = Some(BuildVersion {
+            platform: PLATFORM_TVOS,
+            minos: "7.0.1".to_string(),
+            sdk: "13.0".to_string(),
+            legacy: true,
+        });
+        assert_eq!(binary.build_version().unwrap(), expected);
+
+        // Mac Catalyst only exists as LC_BUILD_VERSION
+        assert!(matches!(
+            binary.set_platform(PLATFORM_MACCATALYST),
+            Err(Error::UnsupportedPlatform(PLATFORM_MACCATALYST))
+        ));
+        assert_eq!(binary.build_version().unwrap(), expected);
+    }
+
+    #[test]
+    fn remove_code_signature_shrinks_linkedit() {
+        // the signature pushes __LINKEDIT into a second page, removing it drops that page
+        let symbols = 0x3ff0;
+        let mut linkedit = vec![1; symbols];
+        linkedit.extend_from_slice(&[2; 0x200]);
+
+        let data = build_binary(64, &linkedit, |linkedit_offset| {
+            vec![
+                linkedit_data(LC_CODE_SIGNATURE, linkedit_offset + symbols, 0x200),
+                // only the exact name is __LINKEDIT
+                segment("__LINKEDIT_OLD", 0x2_0000_0000, 0, 0, None),
+            ]
+        });
+        let mut binary = MachOBinary::parse(&data).unwrap();
+        assert_eq!(segment_sizes(&binary, "__LINKEDIT"), (0x8000, 0x41f0));
+
+        binary.remove_code_signature().unwrap();
+
+        assert!(!has_command(&binary, LC_CODE_SIGNATURE));
+        assert_eq!(segment_sizes(&binary, "__LINKEDIT"), (0x4000, 0x3ff0));
+        assert_eq!(segment_sizes(&binary, "__LINKEDIT_OLD"), (0, 0));
+        assert_eq!(binary.data.len(), data.len() - 0x200);
+        assert!(binary.data.ends_with(&[1; 16]));
+    }
+
+    #[test]
+    fn full_header_reports_required_and_available() {
+        let data = build_binary(0, &[6; 32], |linkedit_offset| {
+            vec![linkedit_data(LC_CODE_SIGNATURE, linkedit_offset, 32)]
+        });
+        let mut binary = MachOBinary::parse(&data).unwrap();
+
+        let result = binary.add_dylib_load_path("@rpath/Foo.framework/Foo");
+
+        assert!(matches!(
+            result,
+            Err(Error::MachOLoadCommandSpace {
+                required: 56,
+                available: 0,
+            })
+        ));
+        assert_eq!(binary.data, &data[..]);
+    }
+
+    #[test]
+    fn full_header_reclaims_slack_and_unused_commands() {
+        // optimization hints, symbols, strings, signature
+        let mut linkedit = vec![3; 16];
+        linkedit.extend_from_slice(&[4; 32]);
+        linkedit.extend_from_slice(&[5; 16]);
+        linkedit.extend_from_slice(&[6; 32]);
+
+        // 8 bytes of slack in the rpath and two 16 byte commands, 32 bytes are needed
+        let data = build_binary(0, &linkedit, |linkedit_offset| {
+            vec![
+                rpath("@executable_path/Frameworks", 48),
+                [LC_SOURCE_VERSION, 16, 0, 0]
+                    .iter()
+                    .flat_map(|value: &u32| value.to_le_bytes())
+                    .collect(),
+                linkedit_data(LC_LINKER_OPTIMIZATION_HINT, linkedit_offset, 16),
+                symtab(linkedit_offset + 16, 2, linkedit_offset + 48, 16),
+                linkedit_data(LC_CODE_SIGNATURE, linkedit_offset + 64, 32),
+            ]
+        });
+        let linkedit_offset = data.len() - linkedit.len();
+        let mut binary = MachOBinary::parse(&data).unwrap();
+
+        binary.add_rpath("@loader_path").unwrap();
+
+        assert_eq!(
+            binary.rpaths().unwrap(),
+            ["@executable_path/Frameworks", "@loader_path"]
+        );
+        assert!(!has_command(&binary, LC_SOURCE_VERSION));
+        assert!(!has_command(&binary, LC_LINKER_OPTIMIZATION_HINT));
+
+        // the hints are cut out of __LINKEDIT and everything after them moves down
+        assert_eq!(binary.data.len(), data.len() - 16);
+        assert_eq!(segment_sizes(&binary, "__LINKEDIT"), (0x4000, 80));
+        for load_cmd in &binary.macho.load_commands {
+            match &load_cmd.command {
+                CommandVariant::Symtab(symtab) => {
+                    assert_eq!(symtab.symoff as usize, linkedit_offset);
+                    assert_eq!(symtab.stroff as usize, linkedit_offset + 32);
+                }
+                CommandVariant::CodeSignature(command) => {
+                    assert_eq!(command.dataoff as usize, linkedit_offset + 48);
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(&binary.data[linkedit_offset..], &linkedit[16..]);
+    }
+}
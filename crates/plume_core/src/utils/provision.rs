@@ -1,12 +1,10 @@
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::Path;
 
 use crate::Error;
 use crate::utils::TEAM_ID_REGEX;
 use plist::{Date, Dictionary, Value};
 
-use super::MachO;
-
 #[derive(Clone)]
 pub struct MobileProvision {
     pub data: Vec<u8>,
@@ -32,33 +30,6 @@ impl MobileProvision {
         })
     }
 
-    pub fn merge_entitlements(
-        &mut self,
-        binary_path: PathBuf,
-        new_application_id: &str,
-    ) -> Result<(), Error> {
-        let macho = MachO::new(&binary_path)?;
-        let binary_entitlements = macho
-            .entitlements()
-            .clone()
-            .ok_or(Error::ProvisioningEntitlementsUnknown)?;
-
-        let new_team_id = self
-            .entitlements
-            .get("com.apple.developer.team-identifier")
-            .and_then(Value::as_string)
-            .map(|s| s.to_owned());
-
-        crate::utils::merge_entitlements(
-            &mut self.entitlements,
-            &binary_entitlements,
-            &new_team_id,
-            &Some(new_application_id.to_string()),
-        );
-
-        Ok(())
-    }
-
     pub fn entitlements(&self) -> &Dictionary {
         &self.entitlements
     }
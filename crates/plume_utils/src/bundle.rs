@@ -1,11 +1,22 @@
 use super::PlistInfoTrait;
-use crate::Error;
+use crate::{Error, InfoPlistEditor, UrlSchemeRewrite, icon, strings_file};
 use goblin::mach::{
     fat::FAT_MAGIC,
     header::{MH_MAGIC, MH_MAGIC_64},
 };
 use plist::Value;
-use std::{fs, path::PathBuf};
+use plume_core::MachO;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+/// Keys in `InfoPlist.strings` that hold the name shown on the home screen.
+const LOCALIZED_NAME_KEYS: &[&str] = &["CFBundleDisplayName", "CFBundleName"];
+/// Kept next to renamed `CFBundleURLSchemes`, so renaming again starts from the
+/// schemes the app shipped with.
+const ORIGINAL_URL_SCHEMES_KEY: &str = "PlumeOriginalURLSchemes";
 
 #[derive(Debug, Clone)]
 pub struct Bundle {
@@ -44,6 +55,28 @@ impl Bundle {
         &self.bundle_type
     }
 
+    /// Path to the bundle's main binary, the file itself for dylibs.
+    /// Returns `None` if there is no such file.
+    pub fn executable_path(&self) -> Option<PathBuf> {
+        let path = if self.bundle_type == BundleType::Dylib {
+            self.bundle_dir.clone()
+        } else {
+            self.bundle_dir.join(self.get_executable()?)
+        };
+
+        path.is_file().then_some(path)
+    }
+
+    /// The app icon as a standard PNG, from loose icon files or `Assets.car`.
+    pub fn app_icon_data(&self) -> Option<Vec<u8>> {
+        if self.bundle_type == BundleType::Dylib {
+            return None;
+        }
+
+        let plist = plist::from_file(&self.info_plist_path).ok()?;
+        icon::find_app_icon(&plist, |name| fs::read(self.bundle_dir.join(name)).ok())
+    }
+
     pub fn collect_nested_bundles(&self) -> Result<Vec<Bundle>, Error> {
         collect_embeded_bundles_from_dir(&self.bundle_dir)
     }
@@ -56,28 +89,312 @@ impl Bundle {
 
         Ok(bundles)
     }
+
+    /// Returns every binary in the bundle that is still FairPlay encrypted
+    /// (`LC_ENCRYPTION_INFO` with a non-zero `cryptid`).
+    pub fn encrypted_binaries(&self) -> Result<Vec<PathBuf>, Error> {
+        let mut encrypted = Vec::new();
+
+        for bundle in self.collect_bundles_sorted()? {
+            if *bundle.bundle_type() == BundleType::Unknown {
+                continue;
+            }
+
+            let Some(binary_path) = bundle.executable_path() else {
+                continue;
+            };
+
+            let Ok(macho) = MachO::new(&binary_path) else {
+                continue;
+            };
+
+            if macho.is_encrypted()? {
+                encrypted.push(binary_path);
+            }
+        }
+
+        Ok(encrypted)
+    }
+
+    /// Errors with [`Error::BundleEncrypted`] listing every encrypted binary, these
+    /// install fine but crash on launch once re-signed.
+    pub fn ensure_unencrypted(&self) -> Result<(), Error> {
+        let encrypted = self.encrypted_binaries()?;
+        if encrypted.is_empty() {
+            return Ok(());
+        }
+
+        let root = self.bundle_dir().parent().unwrap_or(self.bundle_dir());
+        let names = encrypted
+            .iter()
+            .map(|p| p.strip_prefix(root).unwrap_or(p).display().to_string())
+            .collect();
+
+        Err(Error::BundleEncrypted(names))
+    }
+
+    /// Nested apps and extensions (Watch apps, widgets, sticker packs, ...) relative to
+    /// the bundle, e.g. `PlugIns/Widget.appex`. Ones inside another component go with it
+    /// and aren't listed.
+    pub fn removable_components(&self) -> Result<Vec<String>, Error> {
+        let mut components: Vec<String> = self
+            .collect_nested_bundles()?
+            .iter()
+            .filter(|b| b.bundle_type.should_have_entitlements())
+            .filter_map(|b| b.bundle_dir.strip_prefix(&self.bundle_dir).ok())
+            .map(|path| path.to_string_lossy().replace('\\', "/"))
+            .collect();
+        components.sort();
+
+        let all = components.clone();
+        components.retain(|c| !all.iter().any(|other| c.starts_with(&format!("{other}/"))));
+
+        Ok(components)
+    }
+
+    /// Deletes nested components as listed by [`Bundle::removable_components`], so they
+    /// don't need App IDs of their own.
+    pub fn remove_components(&self, components: &[String]) -> Result<(), Error> {
+        for component in components {
+            let relative = Path::new(component);
+            let is_component = relative
+                .extension()
+                .is_some_and(|ext| ext == "app" || ext == "appex");
+
+            if !is_component
+                || relative
+                    .components()
+                    .any(|c| !matches!(c, Component::Normal(_)))
+            {
+                return Err(Error::Other(format!(
+                    "Not a removable component: {component}"
+                )));
+            }
+
+            let path = self.bundle_dir.join(relative);
+            if !path.is_dir() {
+                log::warn!("Component {component} not found, skipping");
+                continue;
+            }
+
+            log::info!("Removing {component}");
+            fs::remove_dir_all(path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Removes every `*.lproj` of this and nested bundles except `Base`, each bundle's
+    /// development region and the locales in `keep`. Matching goes both ways along the
+    /// region, `pt` keeps `pt-BR.lproj` and `en-US` keeps `en.lproj`.
+    pub fn remove_localizations(&self, keep: &[String]) -> Result<(), Error> {
+        let keep: Vec<String> = keep
+            .iter()
+            .map(|locale| normalize_locale(locale.trim_end_matches(".lproj")))
+            .chain(["base".to_string()])
+            .collect();
+
+        for bundle in self.collect_bundles_sorted()? {
+            if matches!(bundle.bundle_type, BundleType::Dylib | BundleType::Unknown) {
+                continue;
+            }
+
+            let development_region = bundle
+                .edit_info_plist()
+                .ok()
+                .and_then(|info| {
+                    info.get("CFBundleDevelopmentRegion")
+                        .and_then(Value::as_string)
+                        .map(normalize_locale)
+                })
+                .unwrap_or_else(|| "en".to_string());
+
+            for lproj in bundle.localizations()? {
+                let Some(locale) = lproj.file_stem().and_then(|s| s.to_str()) else {
+                    continue;
+                };
+                let locale = normalize_locale(locale);
+
+                let kept = keep.iter().chain([&development_region]).any(|k| {
+                    *k == locale
+                        || k.starts_with(&format!("{locale}-"))
+                        || locale.starts_with(&format!("{k}-"))
+                });
+                if !kept {
+                    fs::remove_dir_all(&lproj)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Lowercases a locale, uses `-` as separator and maps the old English-name `.lproj`
+/// folders to their codes.
+fn normalize_locale(locale: &str) -> String {
+    const LEGACY_NAMES: &[(&str, &str)] = &[
+        ("english", "en"),
+        ("french", "fr"),
+        ("german", "de"),
+        ("italian", "it"),
+        ("japanese", "ja"),
+        ("spanish", "es"),
+        ("dutch", "nl"),
+    ];
+
+    let locale = locale.to_lowercase().replace('_', "-");
+    LEGACY_NAMES
+        .iter()
+        .find(|(name, _)| *name == locale)
+        .map(|(_, code)| code.to_string())
+        .unwrap_or(locale)
 }
 
 impl Bundle {
+    /// Opens the bundle's Info.plist for a batch of edits, see [`InfoPlistEditor`].
+    pub fn edit_info_plist(&self) -> Result<InfoPlistEditor, Error> {
+        InfoPlistEditor::open(&self.info_plist_path)
+    }
+
     pub fn set_info_plist_key<V: Into<Value>>(&self, key: &str, value: V) -> Result<(), Error> {
-        let mut plist = Value::from_file(&self.info_plist_path)?;
-        if let Some(dict) = plist.as_dictionary_mut() {
-            dict.insert(key.to_string(), value.into());
+        let mut info = self.edit_info_plist()?;
+        info.set(key, value);
+        info.save()
+    }
+
+    /// Renames the bundle, including every localized name in `*.lproj/InfoPlist.strings`.
+    pub fn set_name(&self, new_name: &str) -> Result<(), Error> {
+        let mut info = self.edit_info_plist()?;
+        info.set("CFBundleDisplayName", new_name)
+            .set("CFBundleName", new_name);
+        info.save()?;
+
+        self.rename_localizations(new_name)
+    }
+
+    /// Rewrites the localized names in `*.lproj/InfoPlist.strings`, Info.plist is left alone.
+    pub(crate) fn rename_localizations(&self, new_name: &str) -> Result<(), Error> {
+        for lproj in self.localizations()? {
+            strings_file::set_values(
+                &lproj.join("InfoPlist.strings"),
+                LOCALIZED_NAME_KEYS,
+                new_name,
+                false,
+            )?;
         }
-        plist.to_file_xml(&self.info_plist_path)?;
 
         Ok(())
     }
 
-    // TODO: we need to support changing lproj infoplist strings so localized names change as well
-    pub fn set_name(&self, new_name: &str) -> Result<(), Error> {
-        self.set_info_plist_key("CFBundleDisplayName", new_name)?;
-        self.set_info_plist_key("CFBundleName", new_name)
+    /// Sets the name shown for each locale (`ja`, `pt-BR`, ...). Locales the bundle
+    /// isn't localized for are skipped, adding them would change its language list.
+    pub fn set_localized_names(&self, names: &HashMap<String, String>) -> Result<(), Error> {
+        for (locale, name) in names {
+            let locale = locale.trim_end_matches(".lproj");
+            let lproj = self.bundle_dir.join(format!("{locale}.lproj"));
+
+            if !lproj.is_dir() {
+                log::warn!("Bundle has no {locale} localization, skipping its name");
+                continue;
+            }
+
+            strings_file::set_values(
+                &lproj.join("InfoPlist.strings"),
+                LOCALIZED_NAME_KEYS,
+                name,
+                true,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Renames the `CFBundleURLSchemes` of the bundle and the apps and extensions in it.
+    pub fn rewrite_url_schemes(&self, rewrite: &UrlSchemeRewrite) -> Result<(), Error> {
+        for sub_bundle in self.collect_bundles_sorted()? {
+            if !sub_bundle.bundle_type().should_have_entitlements() {
+                continue;
+            }
+
+            let mut info = sub_bundle.edit_info_plist()?;
+            let Some(Value::Array(mut url_types)) = info.get("CFBundleURLTypes").cloned() else {
+                continue;
+            };
+
+            let mut changed = false;
+            for url_type in url_types.iter_mut().filter_map(Value::as_dictionary_mut) {
+                let Some(Value::Array(schemes)) = url_type.get("CFBundleURLSchemes").cloned()
+                else {
+                    continue;
+                };
+                let original = match url_type.get(ORIGINAL_URL_SCHEMES_KEY) {
+                    Some(Value::Array(original)) => original.clone(),
+                    _ => schemes.clone(),
+                };
+
+                let mut renamed = Vec::with_capacity(original.len());
+                for scheme in &original {
+                    match scheme.as_string().and_then(|s| rewrite.rewrite(s)) {
+                        Some(new_scheme) if !is_valid_url_scheme(&new_scheme) => {
+                            return Err(Error::UrlSchemeInvalid(new_scheme));
+                        }
+                        Some(new_scheme) => renamed.push(Value::String(new_scheme)),
+                        None => renamed.push(scheme.clone()),
+                    }
+                }
+                if renamed == schemes {
+                    continue;
+                }
+
+                for (old, new) in original
+                    .iter()
+                    .zip(&renamed)
+                    .filter(|(old, new)| old != new)
+                {
+                    log::info!(
+                        "{}: renamed URL scheme {} to {}",
+                        sub_bundle.bundle_dir().display(),
+                        old.as_string().unwrap_or_default(),
+                        new.as_string().unwrap_or_default()
+                    );
+                }
+                url_type.insert(ORIGINAL_URL_SCHEMES_KEY.to_string(), Value::Array(original));
+                url_type.insert("CFBundleURLSchemes".to_string(), Value::Array(renamed));
+                changed = true;
+            }
+
+            if changed {
+                info.set("CFBundleURLTypes", url_types);
+                info.save()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `*.lproj` directories at the root of the bundle.
+    fn localizations(&self) -> Result<Vec<PathBuf>, Error> {
+        if self.bundle_type == BundleType::Dylib {
+            return Ok(Vec::new());
+        }
+
+        let mut lprojs = Vec::new();
+        for entry in fs::read_dir(&self.bundle_dir)? {
+            let path = entry?.path();
+            if path.is_dir() && path.extension().is_some_and(|ext| ext == "lproj") {
+                lprojs.push(path);
+            }
+        }
+
+        Ok(lprojs)
     }
 
     pub fn set_version(&self, new_version: &str) -> Result<(), Error> {
-        self.set_info_plist_key("CFBundleShortVersionString", new_version)?;
-        self.set_info_plist_key("CFBundleVersion", new_version)
+        let mut info = self.edit_info_plist()?;
+        info.set("CFBundleShortVersionString", new_version)
+            .set("CFBundleVersion", new_version);
+        info.save()
     }
 
     pub fn set_bundle_identifier(&self, new_identifier: &str) -> Result<(), Error> {
@@ -89,56 +406,34 @@ impl Bundle {
         old_identifier: &str,
         new_identifier: &str,
     ) -> Result<(), Error> {
-        let mut did_change = false;
-        let mut plist = Value::from_file(&self.info_plist_path)?;
-
-        // CFBundleIdentifier
-        if let Some(dict) = plist.as_dictionary_mut() {
-            if let Some(Value::String(old_value)) = dict.get("CFBundleIdentifier") {
-                let new_value = old_value.replace(old_identifier, new_identifier);
-                if old_value != &new_value {
-                    dict.insert("CFBundleIdentifier".to_string(), Value::String(new_value));
-                    did_change = true;
-                }
-            }
-
-            // WKCompanionAppBundleIdentifier
-            if let Some(Value::String(old_value)) = dict.get("WKCompanionAppBundleIdentifier") {
-                let new_value = old_value.replace(old_identifier, new_identifier);
-                if old_value != &new_value {
-                    dict.insert(
-                        "WKCompanionAppBundleIdentifier".to_string(),
-                        Value::String(new_value),
-                    );
-                    did_change = true;
-                }
-            }
+        const IDENTIFIER_PATHS: &[&[&str]] = &[
+            &["CFBundleIdentifier"],
+            &["WKCompanionAppBundleIdentifier"],
+            &[
+                "NSExtension",
+                "NSExtensionAttributes",
+                "WKAppBundleIdentifier",
+            ],
+        ];
+
+        let mut info = self.edit_info_plist()?;
+
+        for path in IDENTIFIER_PATHS {
+            let Some(old_value) = info
+                .get_path(path)
+                .and_then(Value::as_string)
+                .map(str::to_string)
+            else {
+                continue;
+            };
 
-            // NSExtension → NSExtensionAttributes → WKAppBundleIdentifier
-            if let Some(Value::Dictionary(extension_dict)) = dict.get_mut("NSExtension") {
-                if let Some(Value::Dictionary(attributes)) =
-                    extension_dict.get_mut("NSExtensionAttributes")
-                {
-                    if let Some(Value::String(old_value)) = attributes.get("WKAppBundleIdentifier")
-                    {
-                        let new_value = old_value.replace(old_identifier, new_identifier);
-                        if old_value != &new_value {
-                            attributes.insert(
-                                "WKAppBundleIdentifier".to_string(),
-                                Value::String(new_value),
-                            );
-                            did_change = true;
-                        }
-                    }
-                }
+            let new_value = old_value.replace(old_identifier, new_identifier);
+            if old_value != new_value {
+                info.set_path(path, new_value);
             }
         }
 
-        if did_change {
-            plist.to_file_xml(&self.info_plist_path)?;
-        }
-
-        Ok(())
+        info.save()
     }
 }
 
@@ -288,3 +583,10 @@ impl BundleType {
         !matches!(self, BundleType::Unknown)
     }
 }
+
+/// A letter followed by letters, digits, `+`, `-` or `.` (RFC 3986).
+fn is_valid_url_scheme(scheme: &str) -> bool {
+    let mut chars = scheme.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+}
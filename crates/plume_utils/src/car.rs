@@ -0,0 +1,354 @@
+//! Compiled asset catalog (`Assets.car`) reader.
+//!
+//! A CAR file is a BOM store: a big-endian table of blocks plus named variables
+//! pointing into it. CoreUI keeps its data in a few of those variables:
+//!
+//! 1. `KEYFORMAT` lists which attribute each slot of a rendition key holds.
+//! 2. `FACETKEYS` is a tree from image set names (`AppIcon`) to their attributes,
+//!    including the identifier shared by all of the set's renditions.
+//! 3. `RENDITIONS` is a tree from rendition keys to a `CTSI` header followed by
+//!    the image data.
+//!
+//! Everything inside the BOM blocks is little-endian. Only what app icons use is
+//! decoded: `ARGB` bitmaps (raw, zlib, LZVN or LZFSE, optionally split into
+//! `KCBC` chunks) and embedded PNG/JPEG data. Newer encodings such as deepmap2
+//! are skipped.
+
+use std::{collections::HashMap, io::Read};
+
+use flate2::read::ZlibDecoder;
+
+use crate::cgbi;
+
+const BOM_MAGIC: &[u8] = b"BOMStore";
+
+/// `kCRThemeAppearanceName`, dark and tinted variants of an icon.
+const ATTRIBUTE_APPEARANCE: u32 = 7;
+/// `kCRThemeIdentifierName`, shared by every rendition of an image set.
+const ATTRIBUTE_IDENTIFIER: u32 = 17;
+
+const PIXEL_FORMAT_ARGB: u32 = u32::from_be_bytes(*b"ARGB");
+const PIXEL_FORMAT_DATA: u32 = u32::from_be_bytes(*b"DATA");
+const PIXEL_FORMAT_JPEG: u32 = u32::from_be_bytes(*b"JPEG");
+
+const COMPRESSION_NONE: u32 = 0;
+const COMPRESSION_ZIP: u32 = 2;
+const COMPRESSION_LZVN: u32 = 3;
+const COMPRESSION_LZFSE: u32 = 4;
+
+/// sizeof(csiheader), the rendition's TLVs and data follow it.
+const CSI_HEADER_SIZE: usize = 184;
+
+/// Icons top out at 1024px, anything much bigger is a broken or hostile catalog.
+const MAX_DIMENSION: u32 = 4096;
+
+/// Returns the largest rendition of the image set `name` as a standard PNG,
+/// preferring the default appearance over dark or tinted variants.
+pub(crate) fn read_image(data: &[u8], name: &str) -> Option<Vec<u8>> {
+    let bom = Bom::parse(data)?;
+
+    let key_format = parse_key_format(bom.var("KEYFORMAT")?)?;
+    let identifier_slot = key_format.iter().position(|&a| a == ATTRIBUTE_IDENTIFIER)?;
+    let appearance_slot = key_format.iter().position(|&a| a == ATTRIBUTE_APPEARANCE);
+
+    let identifier = bom
+        .tree("FACETKEYS")?
+        .into_iter()
+        .find(|(key, _)| key.split(|&b| b == 0).next() == Some(name.as_bytes()))
+        .and_then(|(_, value)| facet_identifier(value))?;
+
+    let mut renditions: Vec<Rendition> = bom
+        .tree("RENDITIONS")?
+        .into_iter()
+        .filter_map(|(key, value)| {
+            let slot = |index: usize| le_u16(key, index * 2);
+            if slot(identifier_slot)? != identifier {
+                return None;
+            }
+
+            let mut rendition = Rendition::parse(value)?;
+            rendition.default_appearance = appearance_slot.and_then(slot).unwrap_or(0) == 0;
+            Some(rendition)
+        })
+        .collect();
+
+    renditions.sort_by_key(|r| (r.default_appearance, r.width as u64 * r.height as u64));
+    renditions.iter().rev().find_map(Rendition::to_png)
+}
+
+struct Bom<'a> {
+    data: &'a [u8],
+    blocks: Vec<(usize, usize)>,
+    vars: HashMap<String, u32>,
+}
+
+impl<'a> Bom<'a> {
+    fn parse(data: &'a [u8]) -> Option<Self> {
+        if !data.starts_with(BOM_MAGIC) {
+            return None;
+        }
+
+        let index_offset = be_u32(data, 16)? as usize;
+        let vars_offset = be_u32(data, 24)? as usize;
+
+        let block_count = be_u32(data, index_offset)? as usize;
+        let mut blocks = Vec::with_capacity(block_count.min(data.len() / 8));
+        for i in 0..block_count {
+            let entry = index_offset + 4 + i * 8;
+            blocks.push((
+                be_u32(data, entry)? as usize,
+                be_u32(data, entry + 4)? as usize,
+            ));
+        }
+
+        let var_count = be_u32(data, vars_offset)?;
+        let mut vars = HashMap::new();
+        let mut pos = vars_offset + 4;
+        for _ in 0..var_count {
+            let block = be_u32(data, pos)?;
+            let length = *data.get(pos + 4)? as usize;
+            let name = data.get(pos + 5..pos + 5 + length)?;
+            vars.insert(String::from_utf8_lossy(name).into_owned(), block);
+            pos += 5 + length;
+        }
+
+        Some(Self { data, blocks, vars })
+    }
+
+    fn block(&self, id: u32) -> Option<&'a [u8]> {
+        let &(offset, length) = self.blocks.get(id as usize)?;
+        self.data.get(offset..offset.checked_add(length)?)
+    }
+
+    fn var(&self, name: &str) -> Option<&'a [u8]> {
+        self.block(*self.vars.get(name)?)
+    }
+
+    /// Every `(key, value)` of a BOM tree, in order.
+    fn tree(&self, name: &str) -> Option<Vec<(&'a [u8], &'a [u8])>> {
+        let tree = self.var(name)?;
+        if !tree.starts_with(b"tree") {
+            return None;
+        }
+
+        // descend to the leftmost leaf, then follow the leaves' forward links
+        let mut paths = self.block(be_u32(tree, 8)?)?;
+        let mut visited = 0;
+        while be_u16(paths, 0)? == 0 {
+            paths = self.block(be_u32(paths, 12)?)?;
+            visited += 1;
+            if visited > self.blocks.len() {
+                return None;
+            }
+        }
+
+        let mut entries = Vec::new();
+        loop {
+            let count = be_u16(paths, 2)? as usize;
+            for i in 0..count {
+                let value = self.block(be_u32(paths, 12 + i * 8)?)?;
+                let key = self.block(be_u32(paths, 16 + i * 8)?)?;
+                entries.push((key, value));
+            }
+
+            let forward = be_u32(paths, 4)?;
+            visited += 1;
+            if forward == 0 || visited > self.blocks.len() {
+                break;
+            }
+            paths = self.block(forward)?;
+        }
+
+        Some(entries)
+    }
+}
+
+/// `renditionkeyfmt`: `kfmt` tag, version, count, then one attribute per key slot.
+fn parse_key_format(data: &[u8]) -> Option<Vec<u32>> {
+    let count = le_u32(data, 8)? as usize;
+    (0..count).map(|i| le_u32(data, 12 + i * 4)).collect()
+}
+
+/// `renditionkeytoken`: hot spot, attribute count, then `(name, value)` pairs.
+fn facet_identifier(data: &[u8]) -> Option<u16> {
+    let count = le_u16(data, 4)? as usize;
+    (0..count)
+        .map(|i| (le_u16(data, 6 + i * 4), le_u16(data, 8 + i * 4)))
+        .find(|(name, _)| *name == Some(ATTRIBUTE_IDENTIFIER as u16))
+        .and_then(|(_, value)| value)
+}
+
+struct Rendition<'a> {
+    width: u32,
+    height: u32,
+    pixel_format: u32,
+    data: &'a [u8],
+    default_appearance: bool,
+}
+
+impl<'a> Rendition<'a> {
+    fn parse(csi: &'a [u8]) -> Option<Self> {
+        if !csi.starts_with(b"ISTC") {
+            return None;
+        }
+
+        let tlv_length = le_u32(csi, 168)? as usize;
+        let data_length = le_u32(csi, 180)? as usize;
+        let start = CSI_HEADER_SIZE.checked_add(tlv_length)?;
+
+        Some(Self {
+            width: le_u32(csi, 12)?,
+            height: le_u32(csi, 16)?,
+            pixel_format: le_u32(csi, 24)?,
+            data: csi.get(start..start.checked_add(data_length)?)?,
+            default_appearance: true,
+        })
+    }
+
+    fn to_png(&self) -> Option<Vec<u8>> {
+        match self.pixel_format {
+            PIXEL_FORMAT_ARGB => self.bitmap_to_png(),
+            PIXEL_FORMAT_DATA | PIXEL_FORMAT_JPEG => {
+                // `RAWD` tag, version, length, then the file as is
+                if !self.data.starts_with(b"DWAR") {
+                    return None;
+                }
+                let length = le_u32(self.data, 8)? as usize;
+                let raw = self.data.get(12..12usize.checked_add(length)?)?.to_vec();
+
+                if raw.starts_with(b"\x89PNG") {
+                    Some(cgbi::normalize(raw))
+                } else {
+                    let image = image::load_from_memory(&raw).ok()?;
+                    let mut png = std::io::Cursor::new(Vec::new());
+                    image.write_to(&mut png, image::ImageFormat::Png).ok()?;
+                    Some(png.into_inner())
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// `CELM` tag, version, compression, length, then BGRA premultiplied rows.
+    fn bitmap_to_png(&self) -> Option<Vec<u8>> {
+        if !self.data.starts_with(b"MLEC")
+            || !(1..=MAX_DIMENSION).contains(&self.width)
+            || !(1..=MAX_DIMENSION).contains(&self.height)
+        {
+            return None;
+        }
+
+        let compression = le_u32(self.data, 8)?;
+        let length = le_u32(self.data, 12)? as usize;
+        let payload = self.data.get(16..16usize.checked_add(length)?)?;
+        let row_length = (self.width as usize).checked_mul(4)?;
+
+        let pixels = decompress(compression, payload, row_length, self.height as usize)?;
+
+        // rows can be padded past width * 4
+        let stride = pixels.len() / self.height as usize;
+        if stride < row_length {
+            return None;
+        }
+        let rows: Vec<u8> = pixels
+            .chunks_exact(stride)
+            .take(self.height as usize)
+            .flat_map(|row| &row[..row_length])
+            .copied()
+            .collect();
+
+        cgbi::encode_bgra(&rows, self.width, self.height)
+    }
+}
+
+fn decompress(
+    compression: u32,
+    payload: &[u8],
+    row_length: usize,
+    height: usize,
+) -> Option<Vec<u8>> {
+    let raw_length = row_length.checked_mul(height)?;
+    // leaves room for padded rows, not for a decompression bomb
+    let limit = raw_length.checked_mul(2)?;
+
+    // the lengths come from the file, so the buffers grow with what's actually
+    // decoded instead of being allocated up front
+    let out = match compression {
+        COMPRESSION_NONE => payload.to_vec(),
+        COMPRESSION_ZIP => {
+            let mut out = Vec::new();
+            ZlibDecoder::new(payload)
+                .take(limit as u64 + 1)
+                .read_to_end(&mut out)
+                .ok()?;
+            out
+        }
+        COMPRESSION_LZVN | COMPRESSION_LZFSE if payload.starts_with(b"KCBC") => {
+            // chunks of rows: tag, two unknowns, row count, length, then the data
+            let mut out = Vec::new();
+            let mut pos = 0;
+            while payload[pos..].starts_with(b"KCBC") {
+                let rows = le_u32(payload, pos + 12)? as usize;
+                let length = le_u32(payload, pos + 16)? as usize;
+                let end = (pos + 20).checked_add(length)?;
+                let chunk = payload.get(pos + 20..end)?;
+                out.extend(lzfse(chunk, rows.checked_mul(row_length)?)?);
+                if out.len() > limit {
+                    return None;
+                }
+                pos = end;
+            }
+            out
+        }
+        COMPRESSION_LZVN | COMPRESSION_LZFSE => lzfse(payload, raw_length)?,
+        _ => return None,
+    };
+
+    (out.len() <= limit).then_some(out)
+}
+
+/// Decodes an LZFSE stream, bare LZVN data gets the `bvxn` block header it lacks.
+fn lzfse(data: &[u8], raw_length: usize) -> Option<Vec<u8>> {
+    let framed;
+    let data = if data.starts_with(b"bvx") {
+        data
+    } else {
+        let raw_length = u32::try_from(raw_length).ok()?;
+        let mut stream = Vec::with_capacity(data.len() + 16);
+        stream.extend_from_slice(b"bvxn");
+        stream.extend_from_slice(&raw_length.to_le_bytes());
+        stream.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        stream.extend_from_slice(data);
+        stream.extend_from_slice(b"bvx$");
+        framed = stream;
+        &framed
+    };
+
+    let mut out = Vec::new();
+    lzfse_rust::decode_bytes(data, &mut out).ok()?;
+    Some(out)
+}
+
+fn be_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn be_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn le_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn le_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
@@ -72,6 +72,37 @@ fn normalize_impl(data: &[u8]) -> Option<Vec<u8>> {
     Some(build_png(&header.ihdr_data, &header.ancillary, &compressed))
 }
 
+/// Encodes raw BGRA premultiplied pixels, as asset catalogs store them, as a
+/// standard PNG. Same pixel fix-up as CgBI files, just without the PNG around it.
+pub(crate) fn encode_bgra(pixels: &[u8], width: u32, height: u32) -> Option<Vec<u8>> {
+    let stride = width as usize * 4;
+    if pixels.len() < height as usize * stride {
+        return None;
+    }
+
+    // unfiltered rows, filter type None
+    let mut filtered = Vec::with_capacity(height as usize * (1 + stride));
+    for row in pixels.chunks_exact(stride).take(height as usize) {
+        filtered.push(0);
+        filtered.extend_from_slice(row);
+    }
+
+    let pixels = unfilter_and_fix_pixels(&filtered, width, height, stride)?;
+    let compressed = recompress(&pixels, stride)?;
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&height.to_be_bytes());
+    // 8-bit RGBA, default compression, filtering and no interlace
+    ihdr.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    let chunks = [
+        (b"IHDR".to_vec(), ihdr.clone()),
+        (b"IEND".to_vec(), Vec::new()),
+    ];
+    Some(build_png(&ihdr, &chunks, &compressed))
+}
+
 struct ImageHeader {
     width: u32,
     height: u32,
@@ -0,0 +1,288 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    path::{Component, Path, PathBuf},
+};
+
+use plume_core::{MachO, MachOExt};
+
+use crate::{Bundle, BundleType, Error};
+
+/// Prefixes dyld resolves from the OS (or its shared cache) rather than the bundle.
+const SYSTEM_PREFIXES: &[&str] = &["/usr/lib/", "/System/"];
+
+/// Live under a system prefix but only exist on jailbroken devices.
+const JAILBREAK_LIBRARIES: &[&str] = &[
+    "/usr/lib/libsubstrate.dylib",
+    "/usr/lib/libsubstitute.dylib",
+    "/usr/lib/libhooker.dylib",
+    "/usr/lib/libellekit.dylib",
+];
+
+/// A single load command of a binary and where it ended up.
+#[derive(Debug, Clone)]
+pub struct Dependency {
+    /// Path as written in the load command, e.g. `@rpath/libcolorpicker.dylib`.
+    pub name: String,
+    /// `LC_LOAD_WEAK_DYLIB`, dyld carries on if it's missing.
+    pub weak: bool,
+    /// File inside the bundle the path resolved to.
+    pub resolved: Option<PathBuf>,
+    /// Provided by the OS, never looked up inside the bundle.
+    pub system: bool,
+}
+
+impl Dependency {
+    /// Missing dependencies that will make dyld abort on launch.
+    pub fn is_unresolved(&self) -> bool {
+        !self.weak && !self.system && self.resolved.is_none()
+    }
+}
+
+/// Dependencies of every binary in a bundle, as dyld would see them on device.
+#[derive(Debug, Clone, Default)]
+pub struct DependencyGraph {
+    pub binaries: HashMap<PathBuf, Vec<Dependency>>,
+}
+
+impl DependencyGraph {
+    /// Every non-weak, non-system dependency that doesn't resolve inside the bundle,
+    /// as `(binary, load path)` pairs.
+    pub fn unresolved(&self) -> Vec<(&PathBuf, &Dependency)> {
+        let mut unresolved: Vec<_> = self
+            .binaries
+            .iter()
+            .flat_map(|(binary, deps)| deps.iter().map(move |dep| (binary, dep)))
+            .filter(|(_, dep)| dep.is_unresolved())
+            .collect();
+        unresolved.sort_by(|a, b| a.0.cmp(b.0).then_with(|| a.1.name.cmp(&b.1.name)));
+        unresolved
+    }
+}
+
+struct BinaryInfo {
+    dependencies: Vec<String>,
+    weak: Vec<String>,
+    rpaths: Vec<String>,
+}
+
+impl Bundle {
+    /// Walks the load commands of every binary in the bundle, starting from each app and
+    /// extension executable so `@rpath` sees the rpaths of the whole loader chain.
+    pub fn dependency_graph(&self) -> Result<DependencyGraph, Error> {
+        let mut infos: HashMap<PathBuf, BinaryInfo> = HashMap::new();
+        let mut binaries = Vec::new();
+        let mut roots = Vec::new();
+
+        for bundle in self.collect_bundles_sorted()? {
+            if *bundle.bundle_type() == BundleType::Unknown {
+                continue;
+            }
+
+            let Some(binary_path) = bundle.executable_path() else {
+                continue;
+            };
+
+            if matches!(
+                bundle.bundle_type(),
+                BundleType::App | BundleType::AppExtension
+            ) {
+                roots.push(binary_path.clone());
+            }
+            binaries.push(binary_path);
+        }
+
+        // rpath search directories each binary inherits from the binaries loading it
+        let mut inherited: HashMap<PathBuf, HashSet<PathBuf>> = HashMap::new();
+        let mut queue: VecDeque<PathBuf> = roots.into_iter().collect();
+        // binaries nothing links against (e.g. loaded with dlopen) still get checked
+        queue.extend(binaries);
+
+        let mut graph = DependencyGraph::default();
+
+        while let Some(binary) = queue.pop_front() {
+            if !infos.contains_key(&binary) {
+                let Some(info) = read_binary_info(&binary)? else {
+                    continue;
+                };
+                infos.insert(binary.clone(), info);
+            }
+            let info = &infos[&binary];
+
+            let executable_dir = executable_dir(&binary);
+            let loader_dir = binary.parent().unwrap_or(Path::new("")).to_path_buf();
+
+            let mut search_dirs: Vec<PathBuf> = info
+                .rpaths
+                .iter()
+                .filter_map(|rpath| expand_path(rpath, &executable_dir, &loader_dir))
+                .collect();
+            if let Some(dirs) = inherited.get(&binary) {
+                search_dirs.extend(dirs.iter().cloned());
+            }
+
+            let mut dependencies = Vec::new();
+            for name in &info.dependencies {
+                let weak = info.weak.contains(name);
+                let (resolved, system) =
+                    resolve_dependency(name, &executable_dir, &loader_dir, &search_dirs);
+
+                if let Some(resolved) = &resolved {
+                    let entry = inherited.entry(resolved.clone()).or_default();
+                    let before = entry.len();
+                    entry.extend(search_dirs.iter().cloned());
+
+                    // revisit once the loader chain hands down new rpaths
+                    if entry.len() != before || !graph.binaries.contains_key(resolved) {
+                        queue.push_back(resolved.clone());
+                    }
+                }
+
+                dependencies.push(Dependency {
+                    name: name.clone(),
+                    weak,
+                    resolved,
+                    system,
+                });
+            }
+
+            graph.binaries.insert(binary, dependencies);
+        }
+
+        Ok(graph)
+    }
+
+    /// Logs every dependency that won't resolve on device, these crash in dyld on launch.
+    pub fn warn_unresolved_dependencies(&self) -> Result<(), Error> {
+        let graph = self.dependency_graph()?;
+        let root = self.bundle_dir().parent().unwrap_or(self.bundle_dir());
+
+        for (binary, dependency) in graph.unresolved() {
+            log::warn!(
+                "Unresolved dependency in {}: {}",
+                binary.strip_prefix(root).unwrap_or(binary).display(),
+                dependency.name
+            );
+        }
+
+        Ok(())
+    }
+}
+
+fn read_binary_info(path: &Path) -> Result<Option<BinaryInfo>, Error> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let Ok(macho) = MachO::new(path) else {
+        return Ok(None);
+    };
+
+    let mut info = BinaryInfo {
+        dependencies: Vec::new(),
+        weak: Vec::new(),
+        rpaths: Vec::new(),
+    };
+
+    // slices usually agree, but union them so nothing slips through
+    for slice in macho.macho_file().iter_macho() {
+        for name in slice.dylib_load_paths()? {
+            if !info.dependencies.contains(&name) {
+                info.dependencies.push(name);
+            }
+        }
+        for name in slice.weak_dylib_load_paths()? {
+            if !info.weak.contains(&name) {
+                info.weak.push(name);
+            }
+        }
+        for rpath in slice.rpaths()? {
+            if !info.rpaths.contains(&rpath) {
+                info.rpaths.push(rpath);
+            }
+        }
+    }
+
+    Ok(Some(info))
+}
+
+/// Returns the file a load path resolves to, and whether it's left to the OS.
+fn resolve_dependency(
+    name: &str,
+    executable_dir: &Path,
+    loader_dir: &Path,
+    search_dirs: &[PathBuf],
+) -> (Option<PathBuf>, bool) {
+    if let Some(rest) = name.strip_prefix("@rpath/") {
+        for dir in search_dirs {
+            let candidate = normalize_path(&dir.join(rest));
+            if is_system_path(&candidate) {
+                return (None, true);
+            }
+            if candidate.is_file() {
+                return (Some(candidate), false);
+            }
+        }
+        return (None, false);
+    }
+
+    match expand_path(name, executable_dir, loader_dir) {
+        Some(path) if is_system_path(&path) => (None, true),
+        // absolute paths like /var/jb/... never point into the bundle
+        Some(_) if name.starts_with('/') => (None, false),
+        Some(path) => (path.is_file().then_some(path), false),
+        None => (None, false),
+    }
+}
+
+/// Expands `@executable_path` and `@loader_path`, absolute paths are returned as is.
+fn expand_path(path: &str, executable_dir: &Path, loader_dir: &Path) -> Option<PathBuf> {
+    let expanded = if let Some(rest) = path.strip_prefix("@executable_path") {
+        executable_dir.join(rest.trim_start_matches('/'))
+    } else if let Some(rest) = path.strip_prefix("@loader_path") {
+        loader_dir.join(rest.trim_start_matches('/'))
+    } else if path.starts_with('/') {
+        PathBuf::from(path)
+    } else {
+        return None;
+    };
+
+    Some(normalize_path(&expanded))
+}
+
+fn is_system_path(path: &Path) -> bool {
+    let Some(path) = path.to_str() else {
+        return false;
+    };
+
+    SYSTEM_PREFIXES.iter().any(|p| path.starts_with(p)) && !JAILBREAK_LIBRARIES.contains(&path)
+}
+
+/// iOS bundles are flat, so the executable sits in the nearest `.app` or `.appex`.
+fn executable_dir(binary: &Path) -> PathBuf {
+    binary
+        .ancestors()
+        .skip(1)
+        .find(|dir| {
+            dir.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| ext == "app" || ext == "appex")
+        })
+        .or_else(|| binary.parent())
+        .unwrap_or(Path::new(""))
+        .to_path_buf()
+}
+
+/// Resolves `.` and `..` without touching the filesystem.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
@@ -9,8 +9,8 @@ use idevice::usbmuxd::{Connection, UsbmuxdAddr, UsbmuxdDevice};
 use idevice::utils::installation;
 use plume_core::MobileProvision;
 
-use crate::Error;
 use crate::options::SignerAppReal;
+use crate::{CancellationToken, Error};
 use idevice::afc::opcode::AfcFopenMode;
 use idevice::house_arrest::HouseArrestClient;
 use idevice::usbmuxd::UsbmuxdConnection;
@@ -219,6 +219,24 @@ impl Device {
         F: FnMut(i32) -> Fut + Send + Clone + 'static,
         Fut: std::future::Future<Output = ()> + Send,
     {
+        self.install_app_cancellable(app_path, &CancellationToken::default(), progress_callback)
+            .await
+    }
+
+    /// Like [`Device::install_app`], but gives up with [`Error::Cancelled`] once `cancel`
+    /// is triggered, dropping the upload or install in progress.
+    pub async fn install_app_cancellable<F, Fut>(
+        &self,
+        app_path: &PathBuf,
+        cancel: &CancellationToken,
+        progress_callback: F,
+    ) -> Result<(), Error>
+    where
+        F: FnMut(i32) -> Fut + Send + Clone + 'static,
+        Fut: std::future::Future<Output = ()> + Send,
+    {
+        cancel.check()?;
+
         if self.usbmuxd_device.is_none() {
             return Err(Error::Other("Device is not connected via USB".to_string()));
         }
@@ -237,8 +255,12 @@ impl Device {
 
         let state = ();
 
-        installation::install_package_with_callback(&provider, app_path, None, callback, state)
-            .await?;
+        tokio::select! {
+            result = installation::install_package_with_callback(
+                &provider, app_path, None, callback, state,
+            ) => result?,
+            _ = cancel.cancelled() => return Err(Error::Cancelled),
+        }
 
         Ok(())
     }
@@ -0,0 +1,604 @@
+//! Fits a binary's entitlements to what its provisioning profile grants.
+//!
+//! installd refuses apps signed with entitlements the profile doesn't back. That
+//! happens all the time when re-signing: free accounts can't enable iCloud or
+//! In-App Purchase, and apps built by another team ask for that team's keychain
+//! and app groups. Reconciling keeps what the profile grants, rewrites what it
+//! grants under another name and drops the rest, recording every change.
+
+use std::fmt;
+
+use plist::{Dictionary, Value};
+
+/// How a binary's value for an entitlement is fitted to what the profile grants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rule {
+    /// Identifies the team and app rather than asking for a capability, always
+    /// taken from the profile with its wildcards filled in.
+    Profile,
+    /// The profile grants a single value, e.g. `aps-environment` is `development`
+    /// or `production` depending on the profile, not on what the app was built with.
+    ProfileValue,
+    /// `TEAMID.` prefixed identifiers, moved over to the profile's team.
+    TeamPrefixed,
+    /// `group.` identifiers, registered with the team ID appended.
+    AppGroups,
+    /// iCloud containers, replaced with the ones registered for the app when the
+    /// profile grants none of the requested ones.
+    Containers,
+    /// Kept as requested where the profile's patterns allow it, never rewritten.
+    /// Associated domains are granted as `*`, which matches any domain rather
+    /// than standing in for the bundle identifier.
+    Verbatim,
+}
+
+const RULES: &[(&str, Rule)] = &[
+    ("application-identifier", Rule::Profile),
+    ("com.apple.developer.team-identifier", Rule::Profile),
+    ("get-task-allow", Rule::Profile),
+    ("keychain-access-groups", Rule::TeamPrefixed),
+    ("com.apple.security.application-groups", Rule::AppGroups),
+    ("aps-environment", Rule::ProfileValue),
+    ("com.apple.developer.associated-domains", Rule::Verbatim),
+    (
+        "com.apple.developer.icloud-container-identifiers",
+        Rule::Containers,
+    ),
+    (
+        "com.apple.developer.ubiquity-container-identifiers",
+        Rule::Containers,
+    ),
+    ("com.apple.developer.icloud-services", Rule::Verbatim),
+    (
+        "com.apple.developer.ubiquity-kvstore-identifier",
+        Rule::ProfileValue,
+    ),
+];
+
+fn rule_for(key: &str) -> Rule {
+    RULES
+        .iter()
+        .find(|(name, _)| *name == key)
+        .map_or(Rule::Verbatim, |(_, rule)| *rule)
+}
+
+/// What reconciling did to one of the binary's entitlements.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EntitlementChange {
+    /// The profile doesn't grant it, e.g. iCloud on a free account.
+    Removed { key: String, value: Value },
+    /// Rewritten to what the profile grants, e.g. another team's keychain group, or
+    /// with the values the profile doesn't grant left out.
+    Changed { key: String, from: Value, to: Value },
+}
+
+impl fmt::Display for EntitlementChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EntitlementChange::Removed { key, .. } => {
+                write!(
+                    f,
+                    "removed {key}, the provisioning profile doesn't grant it"
+                )
+            }
+            EntitlementChange::Changed { key, from, to } => write!(
+                f,
+                "changed {key} from {} to {}",
+                describe(from),
+                describe(to)
+            ),
+        }
+    }
+}
+
+/// Entitlements to sign a binary with: the ones it `requested`, each fitted to what
+/// the profile `granted` by the rule for its key. Wildcards the profile grants
+/// identifiers with are filled in with `bundle_id`.
+pub fn reconcile_entitlements(
+    requested: &Dictionary,
+    granted: &Dictionary,
+    bundle_id: &str,
+) -> (Dictionary, Vec<EntitlementChange>) {
+    let mut entitlements = Dictionary::new();
+    let mut changes = Vec::new();
+
+    for (key, rule) in RULES {
+        if *rule != Rule::Profile {
+            continue;
+        }
+        if let Some(value) = granted.get(key) {
+            entitlements.insert(key.to_string(), resolve_wildcards(value, bundle_id));
+        }
+    }
+
+    for (key, value) in requested {
+        let rule = rule_for(key);
+        if rule == Rule::Profile {
+            continue;
+        }
+
+        let reconciled = granted
+            .get(key)
+            .and_then(|allowed| reconcile_value(rule, value, allowed, bundle_id));
+
+        match reconciled {
+            Some(reconciled) => {
+                if reconciled != *value {
+                    changes.push(EntitlementChange::Changed {
+                        key: key.clone(),
+                        from: value.clone(),
+                        to: reconciled.clone(),
+                    });
+                }
+                entitlements.insert(key.clone(), reconciled);
+            }
+            None => changes.push(EntitlementChange::Removed {
+                key: key.clone(),
+                value: value.clone(),
+            }),
+        }
+    }
+
+    // apps use the default group without asking for it
+    let default_groups = granted
+        .get("keychain-access-groups")
+        .filter(|_| !entitlements.contains_key("keychain-access-groups"));
+    if let Some(groups) = default_groups {
+        entitlements.insert(
+            "keychain-access-groups".to_string(),
+            resolve_wildcards(groups, bundle_id),
+        );
+    }
+
+    (entitlements, changes)
+}
+
+fn reconcile_value(rule: Rule, value: &Value, allowed: &Value, bundle_id: &str) -> Option<Value> {
+    match rule {
+        Rule::Profile => Some(resolve_wildcards(allowed, bundle_id)),
+        Rule::ProfileValue => {
+            if entitlement_allowed(value, allowed) {
+                return Some(value.clone());
+            }
+            match allowed {
+                Value::Array(values) => values.first().map(|v| resolve_wildcards(v, bundle_id)),
+                allowed => Some(resolve_wildcards(allowed, bundle_id)),
+            }
+        }
+        Rule::TeamPrefixed => map_strings(value, |value| {
+            if wildcard_allowed(value, allowed) {
+                return Some(value.to_string());
+            }
+
+            // another team's identifier, e.g. `OTHERTEAM.com.example.shared`
+            let unprefixed = strip_team_prefix(value)?;
+            patterns(allowed).into_iter().find_map(|pattern| {
+                let candidate = format!("{}.{unprefixed}", team_prefix(pattern)?);
+                wildcard_matches(pattern, &candidate).then_some(candidate)
+            })
+        }),
+        Rule::AppGroups => map_strings(value, |value| {
+            if wildcard_allowed(value, allowed) {
+                return Some(value.to_string());
+            }
+
+            // registered as `group.com.example.shared.TEAMID`
+            patterns(allowed)
+                .into_iter()
+                .find(|pattern| {
+                    !pattern.contains('*')
+                        && pattern
+                            .strip_prefix(value)
+                            .and_then(|rest| rest.strip_prefix('.'))
+                            .is_some_and(is_team_id)
+                })
+                .map(str::to_string)
+        }),
+        Rule::Containers => map_strings(value, |value| {
+            wildcard_allowed(value, allowed).then(|| value.to_string())
+        })
+        .or_else(|| {
+            let containers = resolve_wildcards(allowed, bundle_id);
+            match (value, containers) {
+                (Value::Array(_), Value::String(container)) => {
+                    Some(Value::Array(vec![Value::String(container)]))
+                }
+                (Value::Array(_), Value::Array(containers)) if !containers.is_empty() => {
+                    Some(Value::Array(containers))
+                }
+                (Value::String(_), Value::String(container)) => Some(Value::String(container)),
+                _ => None,
+            }
+        }),
+        Rule::Verbatim => {
+            if entitlement_allowed(value, allowed) {
+                return Some(value.clone());
+            }
+            match value {
+                Value::Array(values) => {
+                    let kept: Vec<Value> = values
+                        .iter()
+                        .filter(|value| entitlement_allowed(value, allowed))
+                        .cloned()
+                        .collect();
+                    (!kept.is_empty()).then_some(Value::Array(kept))
+                }
+                _ => None,
+            }
+        }
+    }
+}
+
+/// Maps a string, or every string of an array, dropping the ones `f` has no
+/// replacement for. `None` when nothing is left.
+fn map_strings(value: &Value, f: impl Fn(&str) -> Option<String>) -> Option<Value> {
+    match value {
+        Value::String(value) => f(value).map(Value::String),
+        Value::Array(values) => {
+            let mut kept = Vec::new();
+            for value in values.iter().filter_map(Value::as_string).filter_map(&f) {
+                let value = Value::String(value);
+                if !kept.contains(&value) {
+                    kept.push(value);
+                }
+            }
+            (!kept.is_empty()).then_some(Value::Array(kept))
+        }
+        _ => None,
+    }
+}
+
+fn patterns(allowed: &Value) -> Vec<&str> {
+    match allowed {
+        Value::String(pattern) => vec![pattern.as_str()],
+        Value::Array(patterns) => patterns.iter().filter_map(Value::as_string).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn wildcard_allowed(value: &str, allowed: &Value) -> bool {
+    patterns(allowed)
+        .into_iter()
+        .any(|pattern| wildcard_matches(pattern, value))
+}
+
+/// The team ID `value` starts with, e.g. `ABCDE12345` of `ABCDE12345.com.example`.
+fn team_prefix(value: &str) -> Option<&str> {
+    let (team, _) = value.split_once('.')?;
+    is_team_id(team).then_some(team)
+}
+
+fn is_team_id(value: &str) -> bool {
+    value.len() == 10
+        && value
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+}
+
+fn strip_team_prefix(value: &str) -> Option<&str> {
+    team_prefix(value).map(|team| &value[team.len() + 1..])
+}
+
+fn resolve_wildcards(value: &Value, bundle_id: &str) -> Value {
+    match value {
+        Value::String(s) => Value::String(s.replace('*', bundle_id)),
+        Value::Array(values) => Value::Array(
+            values
+                .iter()
+                .map(|value| resolve_wildcards(value, bundle_id))
+                .collect(),
+        ),
+        Value::Dictionary(dict) => Value::Dictionary(
+            dict.iter()
+                .map(|(key, value)| (key.clone(), resolve_wildcards(value, bundle_id)))
+                .collect(),
+        ),
+        value => value.clone(),
+    }
+}
+
+fn describe(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Boolean(b) => b.to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::Array(values) => format!(
+            "[{}]",
+            values.iter().map(describe).collect::<Vec<_>>().join(", ")
+        ),
+        _ => "...".to_string(),
+    }
+}
+
+/// Whether the profile's `allowed` value grants `signed`. Strings in the profile may
+/// end in wildcards (`TEAMID.*`), arrays grant any combination of their elements.
+pub(crate) fn entitlement_allowed(signed: &Value, allowed: &Value) -> bool {
+    match (signed, allowed) {
+        (Value::String(signed), Value::String(allowed)) => wildcard_matches(allowed, signed),
+        (Value::Boolean(signed), Value::Boolean(allowed)) => !signed || *allowed,
+        (Value::Array(signed), Value::Array(allowed)) => signed
+            .iter()
+            .all(|value| allowed.iter().any(|a| entitlement_allowed(value, a))),
+        // a single value granted through a list
+        (signed, Value::Array(allowed)) => allowed.iter().any(|a| entitlement_allowed(signed, a)),
+        (Value::Array(signed), allowed) => signed
+            .iter()
+            .all(|value| entitlement_allowed(value, allowed)),
+        (Value::Dictionary(signed), Value::Dictionary(allowed)) => {
+            signed.iter().all(|(key, value)| {
+                allowed
+                    .get(key)
+                    .is_some_and(|a| entitlement_allowed(value, a))
+            })
+        }
+        (signed, allowed) => signed == allowed,
+    }
+}
+
+/// Matches `value` against a pattern where `*` stands for any number of characters.
+fn wildcard_matches(pattern: &str, value: &str) -> bool {
+    let Some((prefix, rest)) = pattern.split_once('*') else {
+        return pattern == value;
+    };
+
+    value.strip_prefix(prefix).is_some_and(|value| {
+        (0..=value.len())
+            .filter(|&i| value.is_char_boundary(i))
+            .any(|i| wildcard_matches(rest, &value[i..]))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use plist::{Dictionary, Value};
+
+    use super::{EntitlementChange, reconcile_entitlements};
+
+    fn fixture(name: &str) -> Dictionary {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/entitlements")
+            .join(name);
+        Value::from_file(path).unwrap().into_dictionary().unwrap()
+    }
+
+    fn profile(name: &str) -> Dictionary {
+        fixture(name)
+            .get("Entitlements")
+            .and_then(Value::as_dictionary)
+            .cloned()
+            .unwrap()
+    }
+
+    fn strings(values: &[&str]) -> Value {
+        Value::Array(
+            values
+                .iter()
+                .map(|v| Value::String(v.to_string()))
+                .collect(),
+        )
+    }
+
+    fn removed(changes: &[EntitlementChange]) -> Vec<&str> {
+        let mut keys: Vec<&str> = changes
+            .iter()
+            .filter_map(|change| match change {
+                EntitlementChange::Removed { key, .. } => Some(key.as_str()),
+                EntitlementChange::Changed { .. } => None,
+            })
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    fn changed(changes: &[EntitlementChange]) -> Vec<&str> {
+        let mut keys: Vec<&str> = changes
+            .iter()
+            .filter_map(|change| match change {
+                EntitlementChange::Changed { key, .. } => Some(key.as_str()),
+                EntitlementChange::Removed { .. } => None,
+            })
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn free_account_keeps_what_it_can() {
+        let (entitlements, changes) = reconcile_entitlements(
+            &fixture("app_store_binary.plist"),
+            &profile("free_account.plist"),
+            "com.vendor.app",
+        );
+
+        assert_eq!(
+            entitlements["application-identifier"],
+            Value::from("AAAAAAAAAA.com.vendor.app")
+        );
+        assert_eq!(
+            entitlements["com.apple.developer.team-identifier"],
+            Value::from("AAAAAAAAAA")
+        );
+        assert_eq!(entitlements["get-task-allow"], Value::Boolean(true));
+        assert_eq!(
+            entitlements["keychain-access-groups"],
+            strings(&["AAAAAAAAAA.com.vendor.app", "AAAAAAAAAA.com.vendor.shared"])
+        );
+        assert_eq!(
+            entitlements["com.apple.security.application-groups"],
+            strings(&["group.com.vendor.shared.AAAAAAAAAA"])
+        );
+
+        assert_eq!(
+            changed(&changes),
+            [
+                "com.apple.security.application-groups",
+                "keychain-access-groups"
+            ]
+        );
+        assert_eq!(
+            removed(&changes),
+            [
+                "aps-environment",
+                "beta-reports-active",
+                "com.apple.developer.associated-domains",
+                "com.apple.developer.icloud-container-environment",
+                "com.apple.developer.icloud-container-identifiers",
+                "com.apple.developer.icloud-services",
+                "com.apple.developer.ubiquity-container-identifiers",
+                "com.apple.developer.ubiquity-kvstore-identifier",
+            ]
+        );
+        assert_eq!(entitlements.len(), 5);
+    }
+
+    #[test]
+    fn developer_program_rewrites_capabilities() {
+        let binary = fixture("app_store_binary.plist");
+        let (entitlements, changes) =
+            reconcile_entitlements(&binary, &profile("developer_program.plist"), "com.me.app");
+
+        assert_eq!(
+            entitlements["application-identifier"],
+            Value::from("BBBBBBBBBB.com.me.app")
+        );
+        assert_eq!(entitlements["aps-environment"], Value::from("development"));
+        assert_eq!(
+            entitlements["keychain-access-groups"],
+            strings(&["BBBBBBBBBB.com.vendor.app", "BBBBBBBBBB.com.vendor.shared"])
+        );
+        assert_eq!(
+            entitlements["com.apple.developer.icloud-container-identifiers"],
+            strings(&["iCloud.com.me.app"])
+        );
+        assert_eq!(
+            entitlements["com.apple.developer.ubiquity-container-identifiers"],
+            strings(&["iCloud.com.me.app"])
+        );
+        assert_eq!(
+            entitlements["com.apple.developer.ubiquity-kvstore-identifier"],
+            Value::from("BBBBBBBBBB.com.me.app")
+        );
+
+        // granted as `*`, which matches rather than stands in for the bundle identifier
+        for key in [
+            "com.apple.developer.associated-domains",
+            "com.apple.developer.icloud-services",
+            "com.apple.developer.icloud-container-environment",
+        ] {
+            assert_eq!(entitlements[key], binary[key], "{key}");
+        }
+
+        assert_eq!(
+            changed(&changes),
+            [
+                "aps-environment",
+                "com.apple.developer.icloud-container-identifiers",
+                "com.apple.developer.ubiquity-container-identifiers",
+                "com.apple.developer.ubiquity-kvstore-identifier",
+                "keychain-access-groups",
+            ]
+        );
+        assert_eq!(
+            removed(&changes),
+            [
+                "beta-reports-active",
+                "com.apple.security.application-groups"
+            ]
+        );
+    }
+
+    #[test]
+    fn wildcard_profile_resolves_identifier() {
+        let (entitlements, changes) = reconcile_entitlements(
+            &fixture("extension_binary.plist"),
+            &profile("wildcard.plist"),
+            "com.me.app.widget",
+        );
+
+        assert_eq!(
+            entitlements["application-identifier"],
+            Value::from("BBBBBBBBBB.com.me.app.widget")
+        );
+        assert_eq!(
+            entitlements["keychain-access-groups"],
+            strings(&["BBBBBBBBBB.com.vendor.shared"])
+        );
+        assert_eq!(changed(&changes), ["keychain-access-groups"]);
+        assert_eq!(
+            removed(&changes),
+            [
+                "com.apple.developer.icloud-container-environment",
+                "com.apple.developer.icloud-container-identifiers",
+                "com.apple.developer.icloud-services",
+                "com.apple.security.application-groups",
+            ]
+        );
+    }
+
+    #[test]
+    fn extension_keeps_groups_and_icloud() {
+        let binary = fixture("extension_binary.plist");
+        let (entitlements, changes) = reconcile_entitlements(
+            &binary,
+            &profile("extension_profile.plist"),
+            "com.me.app.widget",
+        );
+
+        assert_eq!(
+            entitlements["application-identifier"],
+            Value::from("BBBBBBBBBB.com.me.app.widget")
+        );
+        assert_eq!(
+            entitlements["com.apple.security.application-groups"],
+            strings(&["group.com.vendor.shared.BBBBBBBBBB"])
+        );
+        assert_eq!(
+            entitlements["com.apple.developer.icloud-container-identifiers"],
+            strings(&["iCloud.com.me.app"])
+        );
+        for key in [
+            "com.apple.developer.icloud-services",
+            "com.apple.developer.icloud-container-environment",
+        ] {
+            assert_eq!(entitlements[key], binary[key], "{key}");
+        }
+
+        assert_eq!(
+            changed(&changes),
+            [
+                "com.apple.developer.icloud-container-identifiers",
+                "com.apple.security.application-groups",
+                "keychain-access-groups",
+            ]
+        );
+        assert!(removed(&changes).is_empty());
+    }
+
+    #[test]
+    fn default_keychain_group() {
+        let (entitlements, changes) =
+            reconcile_entitlements(&Dictionary::new(), &profile("wildcard.plist"), "com.me.app");
+
+        assert_eq!(
+            entitlements["keychain-access-groups"],
+            strings(&["BBBBBBBBBB.com.me.app", "com.apple.token"])
+        );
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn refresh_is_unchanged() {
+        let granted = profile("free_account.plist");
+        let (signed, _) = reconcile_entitlements(
+            &fixture("app_store_binary.plist"),
+            &granted,
+            "com.vendor.app",
+        );
+        let (entitlements, changes) = reconcile_entitlements(&signed, &granted, "com.vendor.app");
+
+        assert_eq!(entitlements, signed);
+        assert!(changes.is_empty());
+    }
+}
@@ -0,0 +1,166 @@
+use std::{
+    fmt,
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{BundleType, EntitlementChange};
+
+/// What happened, see [`SigningEvent`].
+#[derive(Debug, Clone)]
+pub enum SigningEventKind {
+    /// A nested bundle that is about to be signed.
+    BundleDiscovered {
+        bundle: PathBuf,
+        bundle_type: BundleType,
+    },
+    /// The App ID for a bundle identifier exists on the developer portal.
+    AppIdEnsured { identifier: String },
+    /// Capabilities for the binary's entitlements were turned on for an App ID.
+    CapabilitiesEnabled { identifier: String },
+    /// A provisioning profile was fetched and embedded.
+    ProfileDownloaded { identifier: String },
+    /// Entitlements of a bundle were dropped or rewritten to fit its profile.
+    EntitlementsReconciled {
+        bundle: PathBuf,
+        changes: Vec<EntitlementChange>,
+    },
+    /// A bundle got its signature, `cached` when it was already signed and left alone.
+    BundleSigned { bundle: PathBuf, cached: bool },
+    /// A tweak was injected into the app.
+    TweakApplied { tweak: PathBuf },
+    /// Percentage reported by the device while installing.
+    InstallProgress { progress: i32 },
+}
+
+impl fmt::Display for SigningEventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = |path: &PathBuf| {
+            path.file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.display().to_string())
+        };
+
+        match self {
+            SigningEventKind::BundleDiscovered { bundle, .. } => {
+                write!(f, "Found {}", name(bundle))
+            }
+            SigningEventKind::AppIdEnsured { identifier } => {
+                write!(f, "Registered App ID {identifier}")
+            }
+            SigningEventKind::CapabilitiesEnabled { identifier } => {
+                write!(f, "Enabled capabilities for {identifier}")
+            }
+            SigningEventKind::ProfileDownloaded { identifier } => {
+                write!(f, "Downloaded profile for {identifier}")
+            }
+            SigningEventKind::EntitlementsReconciled { bundle, changes } => {
+                let changes: Vec<String> = changes.iter().map(ToString::to_string).collect();
+                write!(
+                    f,
+                    "Adjusted entitlements of {}: {}",
+                    name(bundle),
+                    changes.join("; ")
+                )
+            }
+            SigningEventKind::BundleSigned {
+                bundle,
+                cached: false,
+            } => write!(f, "Signed {}", name(bundle)),
+            SigningEventKind::BundleSigned {
+                bundle,
+                cached: true,
+            } => {
+                write!(f, "Already signed {}", name(bundle))
+            }
+            SigningEventKind::TweakApplied { tweak } => write!(f, "Applied {}", name(tweak)),
+            SigningEventKind::InstallProgress { progress } => {
+                write!(f, "Installing... {progress}%")
+            }
+        }
+    }
+}
+
+/// A step of signing and installing an app.
+#[derive(Debug, Clone)]
+pub struct SigningEvent {
+    /// Increases by one for every event of the same [`SigningEvents`].
+    pub id: u64,
+    /// Time since the [`SigningEvents`] was created.
+    pub elapsed: Duration,
+    /// How long the step took, zero for ones that aren't timed.
+    pub duration: Duration,
+    pub kind: SigningEventKind,
+}
+
+impl fmt::Display for SigningEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if !self.duration.is_zero() {
+            write!(f, " ({:.1}s)", self.duration.as_secs_f64())?;
+        }
+        Ok(())
+    }
+}
+
+/// Receives [`SigningEvent`]s, possibly from several threads at once.
+pub trait SigningObserver: Send + Sync {
+    fn on_event(&self, event: &SigningEvent);
+}
+
+impl<F: Fn(&SigningEvent) + Send + Sync> SigningObserver for F {
+    fn on_event(&self, event: &SigningEvent) {
+        self(event)
+    }
+}
+
+struct Inner {
+    observer: Box<dyn SigningObserver>,
+    next_id: AtomicU64,
+    start: Instant,
+}
+
+/// Hands events to an observer, numbering and timing them. Clones share the
+/// observer and the numbering, so the signer and the app can both emit events.
+#[derive(Clone, Default)]
+pub struct SigningEvents {
+    inner: Option<Arc<Inner>>,
+}
+
+impl SigningEvents {
+    pub fn new<O: SigningObserver + 'static>(observer: O) -> Self {
+        Self {
+            inner: Some(Arc::new(Inner {
+                observer: Box::new(observer),
+                next_id: AtomicU64::new(0),
+                start: Instant::now(),
+            })),
+        }
+    }
+
+    pub fn emit(&self, kind: SigningEventKind) {
+        self.send(Duration::ZERO, kind);
+    }
+
+    /// Emits an event for a step that began at `started`.
+    pub fn emit_since(&self, started: Instant, kind: SigningEventKind) {
+        self.send(started.elapsed(), kind);
+    }
+
+    fn send(&self, duration: Duration, kind: SigningEventKind) {
+        let Some(inner) = &self.inner else {
+            return;
+        };
+
+        inner.observer.on_event(&SigningEvent {
+            id: inner.next_id.fetch_add(1, Ordering::Relaxed),
+            elapsed: inner.start.elapsed(),
+            duration,
+            kind,
+        });
+    }
+}
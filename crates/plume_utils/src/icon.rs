@@ -0,0 +1,177 @@
+use std::path::Path;
+
+use plist::{Dictionary, Value};
+
+use crate::{Error, car, cgbi};
+
+/// Image set actool compiles the app icon into when Info.plist doesn't name one.
+const DEFAULT_ICON_NAME: &str = "AppIcon";
+
+/// `(size name, points, scales)` of icons to render.
+type IconSizes = [(&'static str, f32, &'static [u32])];
+
+/// Every icon iOS looks up on iPhone: notifications, Settings, Spotlight and the
+/// home screen.
+const IPHONE_ICONS: &IconSizes = &[
+    ("20x20", 20.0, &[2, 3]),
+    ("29x29", 29.0, &[2, 3]),
+    ("40x40", 40.0, &[2, 3]),
+    ("60x60", 60.0, &[2, 3]),
+];
+
+/// Same for iPad, `83.5x83.5` is the iPad Pro home screen icon.
+const IPAD_ICONS: &IconSizes = &[
+    ("20x20", 20.0, &[1, 2]),
+    ("29x29", 29.0, &[1, 2]),
+    ("40x40", 40.0, &[1, 2]),
+    ("76x76", 76.0, &[1, 2]),
+    ("83.5x83.5", 83.5, &[2]),
+];
+
+/// Base names of a generated icon set, as listed in `CFBundleIconFiles`.
+pub(crate) struct IconFiles {
+    iphone: Vec<String>,
+    ipad: Vec<String>,
+}
+
+impl IconFiles {
+    /// A `CFBundlePrimaryIcon` / `CFBundleAlternateIcons` entry for the set. It has no
+    /// `CFBundleIconName`, so iOS uses these files instead of the asset catalog.
+    pub(crate) fn icon_entry(&self, ipad: bool) -> Value {
+        let files = if ipad { &self.ipad } else { &self.iphone };
+
+        let mut entry = Dictionary::new();
+        entry.insert(
+            "CFBundleIconFiles".to_string(),
+            Value::Array(files.iter().cloned().map(Value::String).collect()),
+        );
+        entry.insert("UIPrerenderedIcon".to_string(), Value::Boolean(false));
+        Value::Dictionary(entry)
+    }
+}
+
+/// Renders `image` at every iPhone and iPad icon size into `dir`, named
+/// `<prefix><size>@<scale>x[~ipad].png`.
+pub(crate) fn write_icon_set(image: &Path, dir: &Path, prefix: &str) -> Result<IconFiles, Error> {
+    let img = image::open(image)?;
+
+    let write = |icons: &IconSizes, suffix: &str| -> Result<Vec<String>, Error> {
+        let mut names = Vec::new();
+        for &(size_name, points, scales) in icons {
+            let name = format!("{prefix}{size_name}");
+            for &scale in scales {
+                let pixels = (points * scale as f32).round() as u32;
+                let resized =
+                    img.resize_to_fill(pixels, pixels, image::imageops::FilterType::Lanczos3);
+                let file_name = if scale == 1 {
+                    format!("{name}{suffix}.png")
+                } else {
+                    format!("{name}@{scale}x{suffix}.png")
+                };
+                resized.save_with_format(dir.join(file_name), image::ImageFormat::Png)?;
+            }
+            names.push(name);
+        }
+        Ok(names)
+    };
+
+    Ok(IconFiles {
+        iphone: write(IPHONE_ICONS, "")?,
+        ipad: write(IPAD_ICONS, "~ipad")?,
+    })
+}
+
+/// Makes a user-supplied alternate icon name safe to use in file names.
+pub(crate) fn file_name_prefix(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// Finds the app icon of a bundle as a standard PNG. `read` loads a file by its path
+/// relative to the bundle, so this works on packages and extracted bundles alike.
+///
+/// Loose `<name>@2x.png` files are tried first, then the `Assets.car` most apps
+/// built with a recent Xcode only ship.
+pub(crate) fn find_app_icon<F>(plist: &Dictionary, mut read: F) -> Option<Vec<u8>>
+where
+    F: FnMut(&str) -> Option<Vec<u8>>,
+{
+    let suffixes = ["@3x.png", "@2x.png", "@1x.png", ".png"];
+
+    for name in icon_file_names(plist) {
+        for suffix in &suffixes {
+            if let Some(data) = read(&format!("{name}{suffix}")).filter(|d| !d.is_empty()) {
+                return Some(cgbi::normalize(data));
+            }
+        }
+    }
+
+    let catalog = read("Assets.car")?;
+    car::read_image(&catalog, &icon_name(plist))
+}
+
+/// Collects all candidate icon base names from the plist, in order of preference.
+/// CFBundleIcons (iPhone) takes priority, fall back to CFBundleIcons~ipad, then
+/// top-level CFBundleIconFiles.
+fn icon_file_names(plist: &Dictionary) -> Vec<String> {
+    let mut icon_names: Vec<String> = Vec::new();
+
+    let primary_from = |d: &Dictionary| -> Vec<String> {
+        d.get("CFBundlePrimaryIcon")
+            .and_then(|v| v.as_dictionary())
+            .and_then(|d| d.get("CFBundleIconFiles"))
+            .and_then(|v| v.as_array())
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|v| v.as_string())
+                    .map(|s| s.to_string())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default()
+    };
+
+    if let Some(d) = plist.get("CFBundleIcons").and_then(|v| v.as_dictionary()) {
+        icon_names.extend(primary_from(d));
+    }
+    if let Some(d) = plist
+        .get("CFBundleIcons~ipad")
+        .and_then(|v| v.as_dictionary())
+    {
+        for n in primary_from(d) {
+            if !icon_names.contains(&n) {
+                icon_names.push(n);
+            }
+        }
+    }
+    if let Some(arr) = plist.get("CFBundleIconFiles").and_then(|v| v.as_array()) {
+        for n in arr
+            .iter()
+            .filter_map(|v| v.as_string())
+            .map(|s| s.to_string())
+        {
+            if !icon_names.contains(&n) {
+                icon_names.push(n);
+            }
+        }
+    }
+
+    icon_names
+}
+
+/// Name of the icon's image set in `Assets.car`, `CFBundlePrimaryIcon.CFBundleIconName`.
+fn icon_name(plist: &Dictionary) -> String {
+    ["CFBundleIcons", "CFBundleIcons~ipad"]
+        .iter()
+        .find_map(|key| {
+            plist
+                .get(key)?
+                .as_dictionary()?
+                .get("CFBundlePrimaryIcon")?
+                .as_dictionary()?
+                .get("CFBundleIconName")?
+                .as_string()
+        })
+        .unwrap_or(DEFAULT_ICON_NAME)
+        .to_string()
+}
@@ -0,0 +1,142 @@
+use std::{
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
+use plist::{Dictionary, Value};
+
+use crate::Error;
+
+/// Batches edits to an Info.plist, written back in its original binary or XML
+/// format by [`InfoPlistEditor::save`].
+///
+/// Nested keys are addressed by path, e.g.
+/// `&["NSExtension", "NSExtensionAttributes", "WKAppBundleIdentifier"]`.
+#[derive(Debug)]
+pub struct InfoPlistEditor {
+    path: PathBuf,
+    dictionary: Dictionary,
+    binary: bool,
+    changed: bool,
+}
+
+impl InfoPlistEditor {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let data = fs::read(path).map_err(|_| Error::BundleInfoPlistMissing)?;
+
+        let dictionary = Value::from_reader(Cursor::new(&data))?
+            .into_dictionary()
+            .ok_or(Error::BundleInfoPlistMissing)?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            dictionary,
+            binary: data.starts_with(b"bplist"),
+            changed: false,
+        })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.dictionary.get(key)
+    }
+
+    pub fn get_path(&self, path: &[&str]) -> Option<&Value> {
+        let (last, parents) = path.split_last()?;
+        let mut dict = &self.dictionary;
+        for key in parents {
+            dict = dict.get(key)?.as_dictionary()?;
+        }
+        dict.get(last)
+    }
+
+    pub fn set<V: Into<Value>>(&mut self, key: &str, value: V) -> &mut Self {
+        self.dictionary.insert(key.to_string(), value.into());
+        self.changed = true;
+        self
+    }
+
+    /// Sets a nested key, creating (or replacing non-dictionary) parents on the way.
+    pub fn set_path<V: Into<Value>>(&mut self, path: &[&str], value: V) -> &mut Self {
+        let Some((last, parents)) = path.split_last() else {
+            return self;
+        };
+
+        let mut dict = &mut self.dictionary;
+        for key in parents {
+            if dict.get(key).and_then(Value::as_dictionary).is_none() {
+                dict.insert(key.to_string(), Value::Dictionary(Dictionary::new()));
+            }
+            dict = dict
+                .get_mut(key)
+                .and_then(Value::as_dictionary_mut)
+                .expect("just inserted a dictionary");
+        }
+
+        dict.insert(last.to_string(), value.into());
+        self.changed = true;
+        self
+    }
+
+    /// Appends to a nested array the values it doesn't hold yet, creating (or
+    /// replacing a non-array) value at `path`.
+    pub fn append_path(&mut self, path: &[&str], values: &[Value]) -> &mut Self {
+        let mut array = match self.get_path(path) {
+            Some(Value::Array(array)) => array.clone(),
+            _ => Vec::new(),
+        };
+
+        let before = array.len();
+        for value in values {
+            if !array.contains(value) {
+                array.push(value.clone());
+            }
+        }
+
+        if array.len() != before || !matches!(self.get_path(path), Some(Value::Array(_))) {
+            self.set_path(path, Value::Array(array));
+        }
+        self
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        let removed = self.dictionary.remove(key);
+        self.changed |= removed.is_some();
+        removed
+    }
+
+    pub fn remove_path(&mut self, path: &[&str]) -> Option<Value> {
+        let (last, parents) = path.split_last()?;
+        let mut dict = &mut self.dictionary;
+        for key in parents {
+            dict = dict.get_mut(key)?.as_dictionary_mut()?;
+        }
+
+        let removed = dict.remove(last);
+        self.changed |= removed.is_some();
+        removed
+    }
+
+    /// Direct access for edits the helpers above don't cover, always marks the plist changed.
+    pub fn dictionary_mut(&mut self) -> &mut Dictionary {
+        self.changed = true;
+        &mut self.dictionary
+    }
+
+    /// Writes the plist back if anything changed.
+    pub fn save(self) -> Result<(), Error> {
+        if !self.changed {
+            return Ok(());
+        }
+
+        let value = Value::Dictionary(self.dictionary);
+        if self.binary {
+            value.to_file_binary(&self.path)?;
+        } else {
+            value.to_file_xml(&self.path)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,265 @@
+//! Declarative Info.plist edits, so tweaking a key doesn't need a code change.
+//!
+//! A patch document is an XML, binary or JSON plist holding one patch or an array
+//! of them:
+//!
+//! ```json
+//! [
+//!   {
+//!     "target": "app",
+//!     "set": { "UIFileSharingEnabled": true, "NSAppTransportSecurity/NSAllowsArbitraryLoads": true },
+//!     "delete": ["UISupportedDevices"],
+//!     "append": { "UIBackgroundModes": ["audio"] }
+//!   },
+//!   { "target": ["com.example.app.widget"], "set": { "NSExtension/NSExtensionAttributes/Foo": 1 } }
+//! ]
+//! ```
+//!
+//! `target` is `app` (the default), `all` for the app and every nested app and
+//! extension, or bundle identifiers as they were before signing renamed them. Keys
+//! are `/` separated paths into nested dictionaries, created if missing; walking
+//! into a value that isn't a dictionary, or appending to one that isn't an array,
+//! is an error rather than replacing it. Deletes happen first, then sets, then
+//! appends, which skip values the array already holds.
+
+use std::{fs, io::Cursor, path::Path};
+
+use plist::Value;
+
+use crate::{Bundle, Error, InfoPlistEditor, PlistInfoTrait};
+
+/// Which bundles an [`InfoPlistPatch`] edits.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum PatchTarget {
+    /// The main app only.
+    #[default]
+    App,
+    /// The app and every nested app and extension.
+    AllBundles,
+    /// Bundles with these identifiers, the main app included if it's listed.
+    Bundles(Vec<String>),
+}
+
+impl PatchTarget {
+    fn matches(&self, is_app: bool, identifier: Option<&str>) -> bool {
+        match self {
+            PatchTarget::App => is_app,
+            PatchTarget::AllBundles => true,
+            PatchTarget::Bundles(identifiers) => {
+                identifier.is_some_and(|id| identifiers.iter().any(|i| i == id))
+            }
+        }
+    }
+}
+
+/// Edits to the Info.plist of the bundles `target` selects, see the module docs.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct InfoPlistPatch {
+    pub target: PatchTarget,
+    pub set: Vec<(String, Value)>,
+    pub delete: Vec<String>,
+    pub append: Vec<(String, Vec<Value>)>,
+}
+
+impl InfoPlistPatch {
+    pub fn new(target: PatchTarget) -> Self {
+        Self {
+            target,
+            ..Self::default()
+        }
+    }
+
+    pub fn set<V: Into<Value>>(mut self, key: &str, value: V) -> Self {
+        self.set.push((key.to_string(), value.into()));
+        self
+    }
+
+    pub fn delete(mut self, key: &str) -> Self {
+        self.delete.push(key.to_string());
+        self
+    }
+
+    pub fn append(mut self, key: &str, values: Vec<Value>) -> Self {
+        self.append.push((key.to_string(), values));
+        self
+    }
+
+    /// Reads a patch document from an XML, binary or JSON file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Vec<Self>, Error> {
+        let data = fs::read(path)?;
+
+        let value = if matches!(data.trim_ascii_start().first(), Some(b'{' | b'[')) {
+            serde_json::from_slice(&data)
+                .map_err(|e| Error::InfoPlistPatchInvalid(e.to_string()))?
+        } else {
+            Value::from_reader(Cursor::new(&data))?
+        };
+
+        Self::from_value(&value)
+    }
+
+    /// Parses a patch document, a single patch dictionary or an array of them.
+    pub fn from_value(value: &Value) -> Result<Vec<Self>, Error> {
+        match value {
+            Value::Array(patches) => patches.iter().map(Self::from_dictionary).collect(),
+            value => Ok(vec![Self::from_dictionary(value)?]),
+        }
+    }
+
+    fn from_dictionary(value: &Value) -> Result<Self, Error> {
+        let invalid = |message: &str| Error::InfoPlistPatchInvalid(message.to_string());
+
+        let patch = value
+            .as_dictionary()
+            .ok_or_else(|| invalid("a patch must be a dictionary"))?;
+
+        if let Some(key) = patch
+            .keys()
+            .find(|key| !matches!(key.as_str(), "target" | "set" | "delete" | "append"))
+        {
+            return Err(Error::InfoPlistPatchInvalid(format!("unknown field {key}")));
+        }
+
+        let target = match patch.get("target") {
+            None => PatchTarget::App,
+            Some(Value::String(target)) => match target.as_str() {
+                "app" => PatchTarget::App,
+                "all" => PatchTarget::AllBundles,
+                identifier => PatchTarget::Bundles(vec![identifier.to_string()]),
+            },
+            Some(Value::Array(identifiers)) => PatchTarget::Bundles(
+                identifiers
+                    .iter()
+                    .map(|id| id.as_string().map(str::to_string))
+                    .collect::<Option<_>>()
+                    .ok_or_else(|| invalid("target identifiers must be strings"))?,
+            ),
+            Some(_) => return Err(invalid("target must be app, all or bundle identifiers")),
+        };
+
+        let set = match patch.get("set") {
+            None => Vec::new(),
+            Some(set) => set
+                .as_dictionary()
+                .ok_or_else(|| invalid("set must be a dictionary"))?
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+        };
+
+        let delete = match patch.get("delete") {
+            None => Vec::new(),
+            Some(Value::String(key)) => vec![key.clone()],
+            Some(Value::Array(keys)) => keys
+                .iter()
+                .map(|key| key.as_string().map(str::to_string))
+                .collect::<Option<_>>()
+                .ok_or_else(|| invalid("delete keys must be strings"))?,
+            Some(_) => return Err(invalid("delete must be a key or an array of keys")),
+        };
+
+        let append = match patch.get("append") {
+            None => Vec::new(),
+            Some(append) => append
+                .as_dictionary()
+                .ok_or_else(|| invalid("append must be a dictionary"))?
+                .iter()
+                .map(|(key, values)| match values {
+                    Value::Array(values) => (key.clone(), values.clone()),
+                    value => (key.clone(), vec![value.clone()]),
+                })
+                .collect(),
+        };
+
+        Ok(Self {
+            target,
+            set,
+            delete,
+            append,
+        })
+    }
+
+    fn apply(&self, info: &mut InfoPlistEditor) -> Result<(), Error> {
+        for key in &self.delete {
+            info.remove_path(&key_path(key));
+        }
+
+        for (key, value) in &self.set {
+            let path = key_path(key);
+            ensure_dictionaries(info, key, &path)?;
+            info.set_path(&path, value.clone());
+        }
+
+        for (key, values) in &self.append {
+            let path = key_path(key);
+            ensure_dictionaries(info, key, &path)?;
+            if info
+                .get_path(&path)
+                .is_some_and(|value| value.as_array().is_none())
+            {
+                return Err(Error::InfoPlistPatchInvalid(format!(
+                    "{key} isn't an array"
+                )));
+            }
+            info.append_path(&path, values);
+        }
+
+        Ok(())
+    }
+}
+
+impl Bundle {
+    /// Applies every patch to the Info.plist of the bundles it targets, in order.
+    pub(crate) fn apply_info_plist_patches(&self, patches: &[InfoPlistPatch]) -> Result<(), Error> {
+        if patches.is_empty() {
+            return Ok(());
+        }
+
+        for sub_bundle in self.collect_bundles_sorted()? {
+            if !sub_bundle.bundle_type().should_have_entitlements() {
+                continue;
+            }
+
+            let is_app = sub_bundle.bundle_dir() == self.bundle_dir();
+            let identifier = sub_bundle.get_bundle_identifier();
+            let mut matching = patches
+                .iter()
+                .filter(|patch| patch.target.matches(is_app, identifier.as_deref()))
+                .peekable();
+            if matching.peek().is_none() {
+                continue;
+            }
+
+            let mut info = sub_bundle.edit_info_plist()?;
+            for patch in matching {
+                patch.apply(&mut info)?;
+            }
+            info.save()?;
+        }
+
+        Ok(())
+    }
+}
+
+fn key_path(key: &str) -> Vec<&str> {
+    key.split('/').collect()
+}
+
+/// Errors if a parent on `path` exists but isn't a dictionary, which `set_path`
+/// would replace.
+fn ensure_dictionaries(info: &InfoPlistEditor, key: &str, path: &[&str]) -> Result<(), Error> {
+    for depth in 1..path.len() {
+        let parent = &path[..depth];
+        if info
+            .get_path(parent)
+            .is_some_and(|value| value.as_dictionary().is_none())
+        {
+            return Err(Error::InfoPlistPatchInvalid(format!(
+                "{key}: {} isn't a dictionary",
+                parent.join("/")
+            )));
+        }
+    }
+
+    Ok(())
+}
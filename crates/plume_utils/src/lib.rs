@@ -1,33 +1,64 @@
 mod bundle;
+mod cancellation;
+mod car;
 mod cgbi;
+mod dependencies;
 mod device;
+mod entitlements;
+mod events;
+mod icon;
+mod info_plist;
+mod info_plist_patch;
+mod lint;
 mod options;
 mod package;
+mod schedule;
+mod signature_cache;
 mod signer;
+mod strings_file;
 mod tweak;
+mod verify;
 
 use std::path::Path;
 
 pub use bundle::{Bundle, BundleType}; // Bundle helper
+pub use cancellation::CancellationToken; // Cancelling jobs
+pub use dependencies::{Dependency, DependencyGraph}; // Dependency checks
 pub use device::{Device, get_device_for_id, install_app_mac}; // Device helper
+pub use entitlements::{EntitlementChange, reconcile_entitlements}; // Entitlement reconciling
+pub use events::{SigningEvent, SigningEventKind, SigningEvents, SigningObserver}; // Progress events
+pub use info_plist::InfoPlistEditor; // Info.plist editing
+pub use info_plist_patch::{InfoPlistPatch, PatchTarget}; // Info.plist patch files
+pub use lint::{LintIssue, LintMessage, LintReport, LintSeverity}; // Bundle checks
 pub use options::{
-    SignerApp, // Supported app types
+    ComponentEntitlements, // Per-component entitlements
+    SignerApp,             // Supported app types
     SignerAppReal,
     SignerEmbedding,   // Embedding options
     SignerFeatures,    // Feature support options
     SignerInstallMode, // Installation mode
     SignerMode,        // Signing mode
     SignerOptions,     // Main
+    UrlSchemeRewrite,  // URL scheme renaming
 };
 pub use package::Package; // Package helper
 pub use signer::Signer; // Signer
 pub use tweak::Tweak; // Tweak helper
+pub use verify::{VerifyIssue, VerifyMessage, VerifyReport}; // Signature checks
 
 use thiserror::Error as ThisError;
 #[derive(Debug, ThisError)]
 pub enum Error {
     #[error("Info.plist not found")]
     BundleInfoPlistMissing,
+    #[error("Bundle contains FairPlay encrypted binaries, decrypt it first: {}", .0.join(", "))]
+    BundleEncrypted(Vec<String>),
+    #[error("Bundle signature is invalid: {}", .0.join(", "))]
+    BundleSignatureInvalid(Vec<String>),
+    #[error("Invalid Info.plist patch: {0}")]
+    InfoPlistPatchInvalid(String),
+    #[error("Invalid URL scheme: {0}")]
+    UrlSchemeInvalid(String),
     // Device
     #[error("Bundle failed to rename, make sure its available: {0}")]
     BundleFailedToCopy(String),
@@ -43,6 +74,12 @@ pub enum Error {
     Zip(#[from] zip::result::ZipError),
     #[error("Info.plist not found")]
     PackageInfoPlistMissing,
+    #[error(
+        "Not enough disk space to extract package ({required} bytes needed, {available} available)"
+    )]
+    PackageInsufficientSpace { required: u64, available: u64 },
+    #[error("Package entry points outside of where it's extracted: {0}")]
+    PackageUnsafeEntry(String),
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
     #[error("Plist error: {0}")]
@@ -53,6 +90,8 @@ pub enum Error {
     Idevice(#[from] idevice::IdeviceError),
     #[error("Codesign error: {0}")]
     Codesign(#[from] plume_core::AppleCodesignError),
+    #[error("Cancelled")]
+    Cancelled,
     #[error("Other error: {0}")]
     Other(String),
     #[error("Image error: {0}")]
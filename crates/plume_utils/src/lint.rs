@@ -0,0 +1,249 @@
+use std::{collections::HashMap, fmt, path::PathBuf};
+
+use goblin::mach::cputype::{CPU_SUBTYPE_MASK, CPU_TYPE_ARM64, get_arch_name_from_types};
+use plume_core::MachO;
+
+use crate::{Bundle, BundleType, Error, PlistInfoTrait};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LintSeverity {
+    /// Installs, but likely misbehaves or gets flagged later.
+    Warning,
+    /// The device will refuse to install or launch it.
+    Error,
+}
+
+/// Something wrong with a bundle that otherwise only shows up as an install error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LintIssue {
+    /// Info.plist has no `CFBundleExecutable`.
+    MissingExecutableKey,
+    /// `CFBundleExecutable` names a file that isn't in the bundle.
+    MissingExecutable(String),
+    /// The executable isn't a Mach-O binary.
+    InvalidExecutable(String),
+    /// No arm64 slice, e.g. a simulator or Intel build.
+    UnsupportedArchitectures(Vec<String>),
+    /// Info.plist has no `CFBundleIdentifier`.
+    MissingIdentifier,
+    /// Another bundle, relative to the package root, uses the same identifier.
+    DuplicateIdentifier { identifier: String, other: PathBuf },
+    /// `CFBundleShortVersionString` differs from the containing app.
+    VersionMismatch {
+        version: String,
+        app_version: String,
+    },
+    /// App extension without `NSExtensionPointIdentifier`.
+    MissingExtensionPoint,
+}
+
+impl LintIssue {
+    pub fn severity(&self) -> LintSeverity {
+        match self {
+            LintIssue::VersionMismatch { .. } => LintSeverity::Warning,
+            _ => LintSeverity::Error,
+        }
+    }
+}
+
+impl fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintIssue::MissingExecutableKey => write!(f, "Info.plist has no CFBundleExecutable"),
+            LintIssue::MissingExecutable(name) => write!(f, "executable {name} is missing"),
+            LintIssue::InvalidExecutable(name) => {
+                write!(f, "executable {name} is not a Mach-O binary")
+            }
+            LintIssue::UnsupportedArchitectures(archs) => write!(
+                f,
+                "executable has no arm64 slice (found {})",
+                archs.join(", ")
+            ),
+            LintIssue::MissingIdentifier => write!(f, "Info.plist has no CFBundleIdentifier"),
+            LintIssue::DuplicateIdentifier { identifier, other } => write!(
+                f,
+                "bundle identifier {identifier} is also used by {}",
+                other.display()
+            ),
+            LintIssue::VersionMismatch {
+                version,
+                app_version,
+            } => write!(
+                f,
+                "version {version} does not match the app's version {app_version}"
+            ),
+            LintIssue::MissingExtensionPoint => {
+                write!(f, "Info.plist has no NSExtensionPointIdentifier")
+            }
+        }
+    }
+}
+
+/// A [`LintIssue`] and the bundle it was found in.
+#[derive(Debug, Clone)]
+pub struct LintMessage {
+    /// Bundle path relative to the package root, e.g. `Foo.app/PlugIns/Widget.appex`.
+    pub bundle: PathBuf,
+    pub issue: LintIssue,
+}
+
+impl LintMessage {
+    pub fn severity(&self) -> LintSeverity {
+        self.issue.severity()
+    }
+}
+
+impl fmt::Display for LintMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.bundle.display(), self.issue)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LintReport {
+    pub messages: Vec<LintMessage>,
+}
+
+impl LintReport {
+    pub fn errors(&self) -> impl Iterator<Item = &LintMessage> {
+        self.messages
+            .iter()
+            .filter(|m| m.severity() == LintSeverity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &LintMessage> {
+        self.messages
+            .iter()
+            .filter(|m| m.severity() == LintSeverity::Warning)
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+}
+
+impl Bundle {
+    /// Checks the bundle and everything nested in it for problems installd would
+    /// reject, errors first.
+    pub fn lint(&self) -> Result<LintReport, Error> {
+        let root = self.bundle_dir().parent().unwrap_or(self.bundle_dir());
+        let app_version = self.get_version();
+
+        let mut report = LintReport::default();
+        let mut identifiers: HashMap<String, PathBuf> = HashMap::new();
+
+        // outermost first, so duplicates point at the enclosing bundle
+        let mut bundles = self.collect_bundles_sorted()?;
+        bundles.reverse();
+
+        for bundle in &bundles {
+            let bundle_type = bundle.bundle_type();
+            if matches!(bundle_type, BundleType::Unknown | BundleType::Dylib) {
+                continue;
+            }
+
+            let relative = bundle
+                .bundle_dir()
+                .strip_prefix(root)
+                .unwrap_or(bundle.bundle_dir())
+                .to_path_buf();
+            let mut push = |issue| {
+                report.messages.push(LintMessage {
+                    bundle: relative.clone(),
+                    issue,
+                })
+            };
+
+            match bundle.get_executable() {
+                Some(name) => {
+                    if let Some(issue) = lint_executable(bundle, &name) {
+                        push(issue);
+                    }
+                }
+                // resource-only frameworks are fine without one
+                None if *bundle_type != BundleType::Framework => {
+                    push(LintIssue::MissingExecutableKey)
+                }
+                None => {}
+            }
+
+            match bundle.get_bundle_identifier() {
+                Some(identifier) => {
+                    if let Some(other) = identifiers.get(&identifier) {
+                        push(LintIssue::DuplicateIdentifier {
+                            identifier,
+                            other: other.clone(),
+                        });
+                    } else {
+                        identifiers.insert(identifier, relative.clone());
+                    }
+                }
+                None => push(LintIssue::MissingIdentifier),
+            }
+
+            if bundle.bundle_dir() == self.bundle_dir() {
+                continue;
+            }
+
+            if *bundle_type == BundleType::AppExtension {
+                let info = bundle.edit_info_plist()?;
+                let has_extension_point = info
+                    .get_path(&["NSExtension", "NSExtensionPointIdentifier"])
+                    .or_else(|| {
+                        // ExtensionKit extensions declare it under their own key
+                        info.get_path(&["EXAppExtensionAttributes", "EXExtensionPointIdentifier"])
+                    })
+                    .is_some();
+
+                if !has_extension_point {
+                    push(LintIssue::MissingExtensionPoint);
+                }
+            }
+
+            if matches!(bundle_type, BundleType::App | BundleType::AppExtension) {
+                match (bundle.get_version(), &app_version) {
+                    (Some(version), Some(app_version)) if &version != app_version => {
+                        push(LintIssue::VersionMismatch {
+                            version,
+                            app_version: app_version.clone(),
+                        });
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        report
+            .messages
+            .sort_by_key(|m| std::cmp::Reverse(m.severity()));
+        Ok(report)
+    }
+}
+
+fn lint_executable(bundle: &Bundle, name: &str) -> Option<LintIssue> {
+    let Some(path) = bundle.executable_path() else {
+        return Some(LintIssue::MissingExecutable(name.to_string()));
+    };
+
+    let Ok(macho) = MachO::new(&path) else {
+        return Some(LintIssue::InvalidExecutable(name.to_string()));
+    };
+
+    let mut architectures = Vec::new();
+    let mut has_arm64 = false;
+    for slice in macho.macho_file().iter_macho() {
+        let header = &slice.macho.header;
+        has_arm64 |= header.cputype == CPU_TYPE_ARM64;
+        architectures.push(
+            get_arch_name_from_types(header.cputype, header.cpusubtype & !CPU_SUBTYPE_MASK)
+                .unwrap_or("unknown")
+                .to_string(),
+        );
+    }
+
+    (!has_arm64).then_some(LintIssue::UnsupportedArchitectures(architectures))
+}
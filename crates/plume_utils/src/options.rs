@@ -1,17 +1,36 @@
-use std::path::PathBuf;
+use std::{collections::HashMap, path::PathBuf};
+
+use crate::{InfoPlistPatch, PatchTarget};
 
 /// Settings for the signer process.
 #[derive(Clone, Debug)]
 pub struct SignerOptions {
     /// Custom app name override.
     pub custom_name: Option<String>,
+    /// Per-locale app name overrides (e.g. `ja` → name), applied after `custom_name`.
+    pub custom_localized_names: HashMap<String, String>,
     /// Custom bundle identifier override.
     pub custom_identifier: Option<String>,
     /// Custom version override.
     pub custom_version: Option<String>,
+    /// Icon image to render at every iPhone and iPad size, replacing the app's icon.
     pub custom_icon: Option<PathBuf>,
+    /// Extra `CFBundleAlternateIcons` as `(name, image)`, for apps that let users pick
+    /// their icon.
+    pub custom_alternate_icons: Vec<(String, PathBuf)>,
+    /// Minimum OS version to apply to every Info.plist and binary (e.g. `12.0`).
+    pub minimum_os_version: Option<String>,
     /// Custom entitlements plist to embed (only used when single_profile is set).
     pub custom_entitlements: Option<PathBuf>,
+    /// Entitlements for single components, keyed by a path relative to the app
+    /// (`PlugIns/Widget.appex`, `.` for the app itself) or by bundle identifier.
+    pub component_entitlements: HashMap<String, ComponentEntitlements>,
+    /// Renames the URL schemes of the app and its extensions, so a second copy of an
+    /// app gets its own OAuth callbacks.
+    pub url_scheme_rewrite: Option<UrlSchemeRewrite>,
+    /// Info.plist edits applied after the built-in ones of `features`, see
+    /// [`InfoPlistPatch::load`].
+    pub info_plist_patches: Vec<InfoPlistPatch>,
     /// Feature support options.
     pub features: SignerFeatures,
     /// Embedding options.
@@ -26,16 +45,33 @@ pub struct SignerOptions {
     pub app: SignerApp,
     /// Apply autorefresh
     pub refresh: bool,
+    /// Top-level package entries besides `Payload/` to leave out when repacking.
+    pub strip_package_entries: Vec<String>,
+    /// Nested apps and extensions to delete before registering, relative to the app
+    /// (e.g. `PlugIns/Widget.appex`), see `Bundle::removable_components`.
+    pub remove_components: Vec<String>,
+    /// When set, every `.lproj` besides `Base`, the development region and these
+    /// locales is deleted.
+    pub keep_localizations: Option<Vec<String>>,
+    /// Directory remembering which frameworks and dylibs are already signed with this
+    /// identity, unchanged ones are skipped when the same bundle is signed again.
+    pub signature_cache: Option<PathBuf>,
 }
 
 impl Default for SignerOptions {
     fn default() -> Self {
         SignerOptions {
             custom_name: None,
+            custom_localized_names: HashMap::new(),
             custom_identifier: None,
             custom_version: None,
             custom_icon: None,
+            custom_alternate_icons: Vec::new(),
+            minimum_os_version: None,
             custom_entitlements: None,
+            component_entitlements: HashMap::new(),
+            url_scheme_rewrite: None,
+            info_plist_patches: Vec::new(),
             features: SignerFeatures::default(),
             embedding: SignerEmbedding::default(),
             mode: SignerMode::default(),
@@ -43,6 +79,10 @@ impl Default for SignerOptions {
             tweaks: None,
             app: SignerApp::Default,
             refresh: false,
+            strip_package_entries: Vec::new(),
+            remove_components: Vec::new(),
+            keep_localizations: None,
+            signature_cache: None,
         }
     }
 }
@@ -65,8 +105,21 @@ impl SignerOptions {
     }
 }
 
+/// Entitlements plist for one component, see `SignerOptions::component_entitlements`.
+/// When signing with a provisioning profile, the result is still reconciled with
+/// what the profile grants, like the binary's own entitlements.
+#[derive(Clone, Debug)]
+pub struct ComponentEntitlements {
+    pub path: PathBuf,
+    /// Use the plist as is instead of adding its keys to the entitlements the
+    /// component would get otherwise.
+    pub replace: bool,
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct SignerFeatures {
+    /// Sets `MinimumOSVersion` to 7.0 in the main Info.plist only, binaries are left
+    /// alone. `SignerOptions::minimum_os_version` overrides it.
     pub support_minimum_os_version: bool,
     pub support_file_sharing: bool,
     pub support_ipad_fullscreen: bool,
@@ -74,7 +127,74 @@ pub struct SignerFeatures {
     pub support_pro_motion: bool,
     pub support_liquid_glass: bool,
     pub support_ellekit: bool,
+    /// Drops `CFBundleURLTypes` from the app and its extensions, see
+    /// `SignerOptions::url_scheme_rewrite` to keep them working under new names.
     pub remove_url_schemes: bool,
+    /// Drops `UISupportedDevices` so the app installs on devices it doesn't list.
+    pub remove_supported_devices: bool,
+}
+
+impl SignerFeatures {
+    /// The Info.plist edits the toggles above stand for.
+    pub fn info_plist_patches(&self) -> Vec<InfoPlistPatch> {
+        let toggles = [
+            (
+                self.support_file_sharing,
+                InfoPlistPatch::new(PatchTarget::App)
+                    .set("UIFileSharingEnabled", true)
+                    .set("UISupportsDocumentBrowser", true),
+            ),
+            (
+                self.support_ipad_fullscreen,
+                InfoPlistPatch::new(PatchTarget::App).set("UIRequiresFullScreen", true),
+            ),
+            (
+                self.support_game_mode,
+                InfoPlistPatch::new(PatchTarget::App).set("GCSupportsGameMode", true),
+            ),
+            (
+                self.support_pro_motion,
+                InfoPlistPatch::new(PatchTarget::App)
+                    .set("CADisableMinimumFrameDurationOnPhone", true),
+            ),
+            (
+                self.support_liquid_glass,
+                InfoPlistPatch::new(PatchTarget::App).set("UIDesignRequiresCompatibility", false),
+            ),
+            (
+                self.remove_url_schemes,
+                InfoPlistPatch::new(PatchTarget::AllBundles).delete("CFBundleURLTypes"),
+            ),
+            (
+                self.remove_supported_devices,
+                InfoPlistPatch::new(PatchTarget::AllBundles).delete("UISupportedDevices"),
+            ),
+        ];
+
+        toggles
+            .into_iter()
+            .filter_map(|(enabled, patch)| enabled.then_some(patch))
+            .collect()
+    }
+}
+
+/// How `CFBundleURLSchemes` are renamed, see `SignerOptions::url_scheme_rewrite`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UrlSchemeRewrite {
+    /// Appended to every scheme, e.g. `-2` turns `myapp` into `myapp-2`.
+    Suffix(String),
+    /// New names for single schemes, the others are left alone.
+    Replace(HashMap<String, String>),
+}
+
+impl UrlSchemeRewrite {
+    /// The new name for `scheme` as the app shipped it, `None` to keep it.
+    pub fn rewrite(&self, scheme: &str) -> Option<String> {
+        match self {
+            UrlSchemeRewrite::Suffix(suffix) => Some(format!("{scheme}{suffix}")),
+            UrlSchemeRewrite::Replace(names) => names.get(scheme).cloned(),
+        }
+    }
 }
 
 /// Embedding options.
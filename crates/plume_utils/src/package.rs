@@ -1,11 +1,17 @@
 use super::{Bundle, PlistInfoTrait};
-use crate::{Error, SignerApp, SignerOptions, cgbi};
+use crate::{CancellationToken, Error, SignerApp, SignerOptions, icon};
 use plist::Dictionary;
-use std::path::PathBuf;
-use std::{env, fs, io::Read};
+use std::{
+    env, fs,
+    io::{Read, Write},
+    path::{Component, Path, PathBuf},
+};
 use uuid::Uuid;
 use zip::ZipArchive;
-use zip::write::FileOptions;
+use zip::write::SimpleFileOptions;
+
+/// Bundle extensions an app can be nested in, used to tell top-level apps apart.
+const BUNDLE_EXTENSIONS: &[&str] = &[".app", ".appex", ".framework", ".bundle"];
 
 #[derive(Debug, Clone)]
 pub struct Package {
@@ -14,212 +20,350 @@ pub struct Package {
     stage_payload_dir: PathBuf,
     info_plist_dictionary: Dictionary,
     archive_entries: Vec<String>,
+    app_paths: Vec<String>,
+    selected_app: usize,
     pub app_icon_data: Option<Vec<u8>>,
 }
 
 impl Package {
+    /// Opens an `.ipa`, `.tipa`, a zip holding a bare `.app`, or an `.xcarchive` directory.
     pub fn new(package_file: PathBuf) -> Result<Self, Error> {
         let stage_dir = env::temp_dir().join(format!(
             "plume_stage_{:08}",
             Uuid::new_v4().to_string().to_uppercase()
         ));
-        let out_package_file = stage_dir.join("stage.ipa");
-
-        fs::create_dir_all(&stage_dir).ok();
-        fs::copy(&package_file, &out_package_file)?;
-
-        let file = fs::File::open(&out_package_file)?;
-        let mut archive = ZipArchive::new(file)?;
-        let archive_entries = (0..archive.len())
-            .filter_map(|i| archive.by_index(i).ok().map(|f| f.name().to_string()))
-            .collect::<Vec<_>>();
 
-        let info_plist_dictionary =
-            Self::get_info_plist_from_archive(&out_package_file, &archive_entries)?;
+        // metadata is read straight from the source, nothing is copied until extraction
+        let mut reader = PackageReader::open(&package_file)?;
+        let archive_entries = reader.entries()?;
+        let app_paths = find_app_paths(&archive_entries);
 
-        let app_icon_data = Self::extract_icon_from_archive(
-            &out_package_file,
-            &archive_entries,
-            &info_plist_dictionary,
-        );
+        if app_paths.is_empty() {
+            return Err(Error::PackageInfoPlistMissing);
+        }
 
-        Ok(Self {
-            package_file: out_package_file,
+        let mut package = Self {
+            package_file,
             stage_dir: stage_dir.clone(),
             stage_payload_dir: stage_dir.join("Payload"),
-            info_plist_dictionary,
+            info_plist_dictionary: Dictionary::new(),
             archive_entries,
-            app_icon_data,
-        })
+            app_paths,
+            selected_app: 0,
+            app_icon_data: None,
+        };
+        package.load_app_metadata(&mut reader)?;
+
+        Ok(package)
+    }
+
+    /// Whether `path` looks like something [`Package::new`] can open.
+    pub fn is_supported_path(path: &Path) -> bool {
+        let Some(ext) = path.extension().and_then(|e| e.to_str()) else {
+            return false;
+        };
+
+        if path.is_dir() {
+            ext == "xcarchive"
+        } else {
+            matches!(ext, "ipa" | "tipa" | "zip")
+        }
     }
 
     pub fn package_file(&self) -> &PathBuf {
         &self.package_file
     }
 
-    fn get_info_plist_from_archive(
-        archive_path: &PathBuf,
-        archive_entries: &[String],
-    ) -> Result<Dictionary, Error> {
-        let file = fs::File::open(archive_path)?;
-        let mut archive = ZipArchive::new(file)?;
-
-        let info_plist_path = archive_entries
+    /// Names of the apps in the package, most have only one.
+    pub fn apps(&self) -> Vec<String> {
+        self.app_paths
             .iter()
-            .find(|entry| {
-                entry.starts_with("Payload/")
-                    && entry.ends_with("/Info.plist")
-                    && entry.matches('/').count() == 2
-            })
-            .ok_or(Error::PackageInfoPlistMissing)?;
+            .map(|path| path.rsplit('/').next().unwrap_or(path).to_string())
+            .collect()
+    }
+
+    pub fn selected_app(&self) -> usize {
+        self.selected_app
+    }
 
-        let mut plist_file = archive.by_name(info_plist_path)?;
-        let mut plist_data = Vec::new();
-        plist_file.read_to_end(&mut plist_data)?;
+    /// Switches to another app from [`Package::apps`], reloading its metadata.
+    pub fn select_app(&mut self, index: usize) -> Result<(), Error> {
+        if index >= self.app_paths.len() {
+            return Err(Error::Other(format!("No app at index {index}")));
+        }
+
+        self.selected_app = index;
+        let mut reader = PackageReader::open(&self.package_file)?;
+        self.load_app_metadata(&mut reader)
+    }
 
-        Ok(plist::from_bytes(&plist_data)?)
+    fn app_path(&self) -> &str {
+        &self.app_paths[self.selected_app]
+    }
+
+    fn app_name(&self) -> &str {
+        let app_path = self.app_path();
+        app_path.rsplit('/').next().unwrap_or(app_path)
+    }
+
+    fn load_app_metadata(&mut self, reader: &mut PackageReader) -> Result<(), Error> {
+        let app_path = self.app_path().to_string();
+
+        let plist_data = reader
+            .read(&format!("{app_path}/Info.plist"))
+            .ok_or(Error::PackageInfoPlistMissing)?;
+        self.info_plist_dictionary = plist::from_bytes(&plist_data)?;
+        self.app_icon_data =
+            Self::extract_icon_from_archive(reader, &app_path, &self.info_plist_dictionary);
+
+        Ok(())
     }
 
     fn extract_icon_from_archive(
-        archive_path: &PathBuf,
-        archive_entries: &[String],
+        reader: &mut PackageReader,
+        app_prefix: &str,
         plist: &Dictionary,
     ) -> Option<Vec<u8>> {
-        // Collects all candidate icon base names from the plist, in order of preference.
-        // CFBundleIcons (iPhone) takes priority, fall back to CFBundleIcons~ipad, then
-        // top-level CFBundleIconFiles.
-        let mut icon_names: Vec<String> = Vec::new();
-
-        let primary_from = |d: &Dictionary| -> Vec<String> {
-            d.get("CFBundlePrimaryIcon")
-                .and_then(|v| v.as_dictionary())
-                .and_then(|d| d.get("CFBundleIconFiles"))
-                .and_then(|v| v.as_array())
-                .map(|arr| {
-                    arr.iter()
-                        .filter_map(|v| v.as_string())
-                        .map(|s| s.to_string())
-                        .collect::<Vec<_>>()
-                })
-                .unwrap_or_default()
+        icon::find_app_icon(plist, |name| reader.read(&format!("{app_prefix}/{name}")))
+    }
+
+    pub fn get_package_bundle(&self) -> Result<Bundle, Error> {
+        self.get_package_bundle_with_progress(|_, _| {})
+    }
+
+    /// Extracts the selected app into `Payload/` of the stage directory, calling
+    /// `progress` with the bytes written so far and the total size.
+    pub fn get_package_bundle_with_progress<F>(&self, progress: F) -> Result<Bundle, Error>
+    where
+        F: FnMut(u64, u64),
+    {
+        self.get_package_bundle_cancellable(&CancellationToken::default(), progress)
+    }
+
+    /// Like [`Package::get_package_bundle_with_progress`], but stops with
+    /// [`Error::Cancelled`] once `cancel` is triggered, removing what was extracted.
+    pub fn get_package_bundle_cancellable<F>(
+        &self,
+        cancel: &CancellationToken,
+        mut progress: F,
+    ) -> Result<Bundle, Error>
+    where
+        F: FnMut(u64, u64),
+    {
+        let mut report = |written, total| {
+            progress(written, total);
+            cancel.check()
         };
 
-        if let Some(d) = plist.get("CFBundleIcons").and_then(|v| v.as_dictionary()) {
-            icon_names.extend(primary_from(d));
+        let app_dir = self.stage_payload_dir.join(self.app_name());
+        let result = self.extract_app(&app_dir, &mut report);
+
+        if let Err(Error::Cancelled) = result {
+            fs::remove_dir_all(&self.stage_dir).ok();
         }
-        if let Some(d) = plist
-            .get("CFBundleIcons~ipad")
-            .and_then(|v| v.as_dictionary())
-        {
-            for n in primary_from(d) {
-                if !icon_names.contains(&n) {
-                    icon_names.push(n);
-                }
-            }
+        result?;
+
+        Bundle::new(app_dir)
+    }
+
+    fn extract_app(
+        &self,
+        app_dir: &Path,
+        progress: &mut dyn FnMut(u64, u64) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        fs::create_dir_all(&self.stage_payload_dir)?;
+        let root = ExtractRoot::new(&self.stage_dir)?;
+
+        if self.package_file.is_dir() {
+            let source = self.package_file.join(self.app_path());
+            let total = dir_size(&source)?;
+            ensure_free_space(&self.stage_dir, total)?;
+
+            let mut written = 0u64;
+            progress(written, total)?;
+            copy_dir_with_progress(&source, app_dir, &root, app_dir, &mut |bytes| {
+                written += bytes;
+                progress(written, total)
+            })?;
+        } else {
+            self.extract_archive(app_dir, &root, progress)?;
         }
-        if let Some(arr) = plist.get("CFBundleIconFiles").and_then(|v| v.as_array()) {
-            for n in arr
-                .iter()
-                .filter_map(|v| v.as_string())
-                .map(|s| s.to_string())
-            {
-                if !icon_names.contains(&n) {
-                    icon_names.push(n);
-                }
+
+        root.verify_symlinks()
+    }
+
+    fn extract_archive(
+        &self,
+        app_dir: &Path,
+        root: &ExtractRoot,
+        progress: &mut dyn FnMut(u64, u64) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let file = fs::File::open(&self.package_file)?;
+        let mut archive = ZipArchive::new(file)?;
+
+        let app_prefix = format!("{}/", self.app_path());
+        let keep_extras = self.app_path().starts_with("Payload/");
+
+        // where each entry goes relative to the stage, apps always land in Payload/
+        let destination = |name: &str| -> Option<PathBuf> {
+            if let Some(rest) = name.strip_prefix(&app_prefix) {
+                return Some(Path::new("Payload").join(self.app_name()).join(rest));
             }
-        }
+            if keep_extras && !name.starts_with("Payload/") {
+                return Some(PathBuf::from(name));
+            }
+            None
+        };
 
-        if icon_names.is_empty() {
-            return None;
+        let mut total = 0u64;
+        for i in 0..archive.len() {
+            let entry = archive.by_index_raw(i)?;
+            if destination(entry.name()).is_some() {
+                total += entry.size();
+            }
         }
 
-        let app_prefix = archive_entries
-            .iter()
-            .find(|e| {
-                e.starts_with("Payload/")
-                    && e.ends_with("/Info.plist")
-                    && e.matches('/').count() == 2
-            })?
-            .trim_end_matches("/Info.plist");
-
-        let file = fs::File::open(archive_path).ok()?;
-        let mut archive = ZipArchive::new(file).ok()?;
-
-        let suffixes = ["@3x.png", "@2x.png", "@1x.png", ".png"];
-
-        for name in &icon_names {
-            for suffix in &suffixes {
-                let candidate = format!("{app_prefix}/{name}{suffix}");
-                if let Ok(mut entry) = archive.by_name(&candidate) {
-                    let mut data = Vec::new();
-                    if entry.read_to_end(&mut data).is_ok() && !data.is_empty() {
-                        return Some(cgbi::normalize(data));
-                    }
+        ensure_free_space(&self.stage_dir, total)?;
+
+        let mut written = 0u64;
+        let mut buffer = vec![0u8; 256 * 1024];
+        progress(written, total)?;
+
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i)?;
+            if entry.enclosed_name().is_none() {
+                log::warn!("Skipping unsafe archive entry: {}", entry.name());
+                continue;
+            }
+            let Some(relative_path) = destination(entry.name()) else {
+                continue;
+            };
+            let out_path = self.stage_dir.join(relative_path);
+
+            if entry.is_dir() {
+                root.create_dir_all(&out_path)?;
+                continue;
+            }
+
+            if entry.is_symlink() {
+                let mut target = String::new();
+                entry.read_to_string(&mut target)?;
+                written += entry.size();
+                // extras like SwiftSupport may link anywhere in the stage, app files only
+                // within the app
+                let within = if out_path.starts_with(app_dir) {
+                    app_dir
+                } else {
+                    &self.stage_dir
+                };
+                root.create_symlink(&target, &out_path, within)?;
+                progress(written, total)?;
+                continue;
+            }
+
+            root.prepare_file(&out_path)?;
+            let mut out_file = fs::File::create(&out_path)?;
+            loop {
+                let read = entry.read(&mut buffer)?;
+                if read == 0 {
+                    break;
                 }
+                out_file.write_all(&buffer[..read])?;
+                written += read as u64;
+                progress(written, total)?;
+            }
+
+            #[cfg(unix)]
+            if let Some(mode) = entry.unix_mode() {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(&out_path, fs::Permissions::from_mode(mode))?;
             }
         }
 
-        None
+        Ok(())
     }
 
-    pub fn get_package_bundle(&self) -> Result<Bundle, Error> {
-        let file = fs::File::open(&self.package_file)?;
-        let mut archive = ZipArchive::new(file)?;
-        archive.extract(&self.stage_dir)?;
+    /// Top-level entries of the package besides `Payload/`, e.g. `SwiftSupport`,
+    /// `Symbols` or `iTunesMetadata.plist`.
+    pub fn extra_top_level_entries(&self) -> Vec<String> {
+        // bare zips and archives only ever contribute the app itself
+        if self.package_file.is_dir() || !self.app_path().starts_with("Payload/") {
+            return Vec::new();
+        }
+
+        let mut entries: Vec<String> = self
+            .archive_entries
+            .iter()
+            .filter_map(|entry| entry.split('/').next())
+            .filter(|name| !name.is_empty() && *name != "Payload")
+            .map(str::to_string)
+            .collect();
+        entries.sort();
+        entries.dedup();
+        entries
+    }
 
-        let app_dir = fs::read_dir(&self.stage_payload_dir)?
-            .filter_map(Result::ok)
-            .map(|e| e.path())
-            .find(|p| p.is_dir() && p.extension().and_then(|e| e.to_str()) == Some("app"))
-            .ok_or_else(|| Error::PackageInfoPlistMissing)?;
+    /// Nested apps and extensions of the selected app, read from the package the same
+    /// way [`Bundle::removable_components`] lists them once extracted.
+    pub fn removable_components(&self) -> Vec<String> {
+        let app_prefix = format!("{}/", self.app_path());
+        let is_component = |name: &str| name.ends_with(".app") || name.ends_with(".appex");
 
-        Ok(Bundle::new(app_dir)?)
+        let mut components: Vec<String> = self
+            .archive_entries
+            .iter()
+            .filter_map(|entry| entry.strip_prefix(&app_prefix)?.strip_suffix("/Info.plist"))
+            .filter(|dir| {
+                let mut parts = dir.split('/');
+                parts.next_back().is_some_and(is_component) && !parts.any(is_component)
+            })
+            .map(str::to_string)
+            .collect();
+        components.sort();
+        components.dedup();
+        components
     }
 
-    pub fn get_archive_based_on_path(&self, path: &PathBuf) -> Result<PathBuf, Error> {
+    /// Returns a package to install or export. When `path` is an extracted bundle the
+    /// stage is repacked, leaving out any top-level entries listed in `strip_entries`.
+    pub fn get_archive_based_on_path(
+        &self,
+        path: &PathBuf,
+        strip_entries: &[String],
+    ) -> Result<PathBuf, Error> {
         if path.is_dir() {
-            self.clone().archive_package_bundle()
+            self.archive_package_bundle(strip_entries)
         } else {
             Ok(self.package_file.clone())
         }
     }
 
-    fn archive_package_bundle(self) -> Result<PathBuf, Error> {
-        let zip_file_path = self.stage_dir.join("resigned.ipa");
-        let file = fs::File::create(&zip_file_path)?;
-        let mut zip = zip::ZipWriter::new(file);
-        let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
-
-        let payload_dir = self.stage_payload_dir;
-
-        fn add_dir_to_zip(
-            zip: &mut zip::ZipWriter<fs::File>,
-            path: &PathBuf,
-            prefix: &PathBuf,
-            options: &FileOptions<'_, zip::write::ExtendedFileOptions>,
-        ) -> Result<(), Error> {
-            for entry in fs::read_dir(path)? {
-                let entry = entry?;
-                let entry_path = entry.path();
-                let name = entry_path
-                    .strip_prefix(prefix)
-                    .map_err(|_| Error::PackageInfoPlistMissing)?
-                    .to_string_lossy()
-                    .to_string();
-
-                if entry_path.is_file() {
-                    zip.start_file(&name, options.clone())?;
-                    let mut f = fs::File::open(&entry_path)?;
-                    std::io::copy(&mut f, zip)?;
-                } else if entry_path.is_dir() {
-                    zip.add_directory(&name, options.clone())?;
-                    add_dir_to_zip(zip, &entry_path, prefix, options)?;
-                }
+    fn archive_package_bundle(&self, strip_entries: &[String]) -> Result<PathBuf, Error> {
+        const ARCHIVE_NAME: &str = "resigned.ipa";
+
+        let zip_file_path = self.stage_dir.join(ARCHIVE_NAME);
+
+        let mut top_level = Vec::new();
+        for entry in fs::read_dir(&self.stage_dir)? {
+            let name = entry?.file_name().to_string_lossy().to_string();
+            if name == ARCHIVE_NAME {
+                continue;
             }
-            Ok(())
+            if strip_entries.contains(&name) {
+                log::info!("Stripping {name} from package");
+                continue;
+            }
+            top_level.push(name);
         }
+        // Payload first like Xcode does, everything else after it in name order
+        top_level.sort_by_key(|name| (name != "Payload", name.clone()));
 
-        add_dir_to_zip(&mut zip, &payload_dir, &self.stage_dir, &options)?;
+        let file = fs::File::create(&zip_file_path)?;
+        let mut zip = zip::ZipWriter::new(file);
+
+        for name in top_level {
+            add_path_to_zip(&mut zip, &self.stage_dir.join(&name), &name)?;
+        }
         zip.finish()?;
 
         Ok(zip_file_path)
@@ -288,3 +432,386 @@ impl Package {
         *settings = new_settings;
     }
 }
+
+/// Reads files out of either a zip package or an `.xcarchive` directory.
+enum PackageReader {
+    Archive(ZipArchive<fs::File>),
+    Directory(PathBuf),
+}
+
+impl PackageReader {
+    fn open(path: &Path) -> Result<Self, Error> {
+        if path.is_dir() {
+            return Ok(Self::Directory(path.to_path_buf()));
+        }
+
+        Ok(Self::Archive(ZipArchive::new(fs::File::open(path)?)?))
+    }
+
+    /// Every file in the package as a `/` separated relative path.
+    fn entries(&self) -> Result<Vec<String>, Error> {
+        match self {
+            Self::Archive(archive) => Ok(archive.file_names().map(str::to_string).collect()),
+            Self::Directory(root) => {
+                let mut entries = Vec::new();
+                collect_dir_entries(root, "", &mut entries)?;
+                Ok(entries)
+            }
+        }
+    }
+
+    fn read(&mut self, name: &str) -> Option<Vec<u8>> {
+        match self {
+            Self::Archive(archive) => {
+                let mut entry = archive.by_name(name).ok()?;
+                let mut data = Vec::new();
+                entry.read_to_end(&mut data).ok()?;
+                Some(data)
+            }
+            Self::Directory(root) => fs::read(root.join(name)).ok(),
+        }
+    }
+}
+
+fn collect_dir_entries(dir: &Path, prefix: &str, entries: &mut Vec<String>) -> Result<(), Error> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = format!("{prefix}{}", entry.file_name().to_string_lossy());
+
+        if entry.file_type()?.is_dir() {
+            collect_dir_entries(&entry.path(), &format!("{name}/"), entries)?;
+        } else {
+            entries.push(name);
+        }
+    }
+
+    Ok(())
+}
+
+/// Finds the apps in a package: `Payload/*.app` in ipas, `Products/Applications/*.app`
+/// in xcarchives and a bare `*.app` anywhere else. Apps nested in other bundles
+/// (watch apps, app clips) are left to the signer.
+fn find_app_paths(entries: &[String]) -> Vec<String> {
+    let is_top_level_app = |dir: &str| {
+        let mut components = dir.split('/');
+        let name = components.next_back().unwrap_or_default();
+
+        name.ends_with(".app")
+            && components
+                .all(|c| c != "__MACOSX" && !BUNDLE_EXTENSIONS.iter().any(|ext| c.ends_with(ext)))
+    };
+
+    let mut apps: Vec<String> = entries
+        .iter()
+        .filter_map(|entry| entry.strip_suffix("/Info.plist"))
+        .filter(|dir| is_top_level_app(dir))
+        .map(str::to_string)
+        .collect();
+    apps.sort();
+    apps.dedup();
+
+    for prefix in ["Payload/", "Products/Applications/"] {
+        let preferred: Vec<String> = apps
+            .iter()
+            .filter(|app| app.starts_with(prefix))
+            .cloned()
+            .collect();
+        if !preferred.is_empty() {
+            return preferred;
+        }
+    }
+
+    apps
+}
+
+fn dir_size(dir: &Path) -> Result<u64, Error> {
+    let mut size = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = fs::symlink_metadata(entry.path())?;
+        if metadata.is_dir() {
+            size += dir_size(&entry.path())?;
+        } else {
+            size += metadata.len();
+        }
+    }
+    Ok(size)
+}
+
+/// Copies `src` to `dst` keeping symlinks and modes, reporting bytes copied.
+/// Symlinks may only point within `within`. Stops at the first error `progress` returns.
+fn copy_dir_with_progress(
+    src: &Path,
+    dst: &Path,
+    root: &ExtractRoot,
+    within: &Path,
+    progress: &mut dyn FnMut(u64) -> Result<(), Error>,
+) -> Result<(), Error> {
+    root.create_dir_all(dst)?;
+
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let src_path = entry.path();
+        let dst_path = dst.join(entry.file_name());
+        let metadata = fs::symlink_metadata(&src_path)?;
+
+        if metadata.file_type().is_symlink() {
+            let target = fs::read_link(&src_path)?;
+            root.create_symlink(&target.to_string_lossy(), &dst_path, within)?;
+        } else if metadata.is_dir() {
+            copy_dir_with_progress(&src_path, &dst_path, root, within, progress)?;
+        } else {
+            root.prepare_file(&dst_path)?;
+            // fs::copy carries the permission bits over
+            fs::copy(&src_path, &dst_path)?;
+            progress(metadata.len())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds `path` as `name`, recursing into directories in name order so the archive
+/// is the same for the same input. Symlinks are stored as symlinks, not followed.
+fn add_path_to_zip(
+    zip: &mut zip::ZipWriter<fs::File>,
+    path: &Path,
+    name: &str,
+) -> Result<(), Error> {
+    let metadata = fs::symlink_metadata(path)?;
+    let options = SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .unix_permissions(file_mode(&metadata));
+
+    if metadata.file_type().is_symlink() {
+        let target = fs::read_link(path)?;
+        // zip stores symlink targets with forward slashes
+        let target = target.to_string_lossy().replace('\\', "/");
+        zip.add_symlink(name, target, options)?;
+    } else if metadata.is_dir() {
+        zip.add_directory(format!("{name}/"), options)?;
+
+        let mut children = fs::read_dir(path)?
+            .map(|entry| entry.map(|e| e.file_name().to_string_lossy().to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+        children.sort();
+
+        for child in children {
+            add_path_to_zip(zip, &path.join(&child), &format!("{name}/{child}"))?;
+        }
+    } else {
+        // ZIP64 extra fields are only needed (and only written) past 4 GB
+        let options = options.large_file(metadata.len() >= u32::MAX as u64);
+        zip.start_file(name, options)?;
+        let mut f = fs::File::open(path)?;
+        std::io::copy(&mut f, zip)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn file_mode(metadata: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o7777
+}
+
+// no mode bits to carry over, fall back to what iOS expects
+#[cfg(not(unix))]
+fn file_mode(metadata: &fs::Metadata) -> u32 {
+    if metadata.is_dir() { 0o755 } else { 0o644 }
+}
+
+/// The directory a package is extracted to. Keeps a crafted package from writing
+/// outside of it, e.g. with `Foo.app/l -> /home/u` followed by `Foo.app/l/.ssh/...`.
+struct ExtractRoot {
+    path: PathBuf,
+    canonical: PathBuf,
+}
+
+impl ExtractRoot {
+    fn new(path: &Path) -> Result<Self, Error> {
+        Ok(Self {
+            path: path.to_path_buf(),
+            canonical: fs::canonicalize(path)?,
+        })
+    }
+
+    /// Creates `dir` and its parents one at a time, refusing to go through symlinks.
+    fn create_dir_all(&self, dir: &Path) -> Result<(), Error> {
+        let relative = dir
+            .strip_prefix(&self.path)
+            .map_err(|_| unsafe_entry(dir))?;
+
+        let mut current = self.path.clone();
+        for component in relative.components() {
+            let Component::Normal(name) = component else {
+                return Err(unsafe_entry(dir));
+            };
+            current.push(name);
+
+            match fs::symlink_metadata(&current) {
+                Ok(metadata) if metadata.file_type().is_symlink() => {
+                    return Err(unsafe_entry(dir));
+                }
+                Ok(metadata) if metadata.is_dir() => {}
+                _ => fs::create_dir(&current)?,
+            }
+        }
+
+        self.ensure_inside(dir)
+    }
+
+    /// Creates the parents of a file about to be written at `path`, refusing to write
+    /// through a symlink.
+    fn prepare_file(&self, path: &Path) -> Result<(), Error> {
+        let parent = path.parent().ok_or_else(|| unsafe_entry(path))?;
+        self.create_dir_all(parent)?;
+
+        if fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink()) {
+            return Err(unsafe_entry(path));
+        }
+
+        Ok(())
+    }
+
+    /// Creates a symlink, refusing targets that leave `within` going by the path alone.
+    fn create_symlink(&self, target: &str, link: &Path, within: &Path) -> Result<(), Error> {
+        if !symlink_stays_inside(link, target, within) {
+            return Err(unsafe_entry(link));
+        }
+
+        let parent = link.parent().ok_or_else(|| unsafe_entry(link))?;
+        self.create_dir_all(parent)?;
+        create_symlink(target, link)
+    }
+
+    /// Checks that every symlink resolves inside the root. A chain of links can get out
+    /// where each one looks fine on its own, e.g. `a/b/x -> ../..` and `a/b/y -> x/../../..`.
+    fn verify_symlinks(&self) -> Result<(), Error> {
+        self.verify_symlinks_in(&self.path)
+    }
+
+    fn verify_symlinks_in(&self, dir: &Path) -> Result<(), Error> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let metadata = fs::symlink_metadata(&path)?;
+
+            if metadata.file_type().is_symlink() {
+                // dangling links don't lead anywhere we could write to
+                let escapes = fs::canonicalize(&path)
+                    .is_ok_and(|resolved| !resolved.starts_with(&self.canonical));
+                if escapes {
+                    return Err(unsafe_entry(&path));
+                }
+            } else if metadata.is_dir() {
+                self.verify_symlinks_in(&path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn ensure_inside(&self, path: &Path) -> Result<(), Error> {
+        if fs::canonicalize(path)?.starts_with(&self.canonical) {
+            Ok(())
+        } else {
+            Err(unsafe_entry(path))
+        }
+    }
+}
+
+/// Whether a symlink at `link` pointing to `target` stays inside `within`, resolving
+/// `..` without looking at the filesystem.
+fn symlink_stays_inside(link: &Path, target: &str, within: &Path) -> bool {
+    let Some(mut resolved) = link.parent().map(Path::to_path_buf) else {
+        return false;
+    };
+
+    for component in Path::new(target).components() {
+        match component {
+            Component::Normal(name) => resolved.push(name),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !resolved.pop() {
+                    return false;
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return false,
+        }
+    }
+
+    resolved.starts_with(within)
+}
+
+fn unsafe_entry(path: &Path) -> Error {
+    Error::PackageUnsafeEntry(path.display().to_string())
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &str, link: &Path) -> Result<(), Error> {
+    fs::remove_file(link).ok();
+    std::os::unix::fs::symlink(target, link)?;
+    Ok(())
+}
+
+// symlinks need extra privileges on windows, write the target like git does
+#[cfg(not(unix))]
+fn create_symlink(target: &str, link: &Path) -> Result<(), Error> {
+    fs::write(link, target)?;
+    Ok(())
+}
+
+/// Errors with [`Error::PackageInsufficientSpace`] if `dir` can't hold `required` more bytes.
+fn ensure_free_space(dir: &Path, required: u64) -> Result<(), Error> {
+    let Some(available) = available_space(dir) else {
+        return Ok(());
+    };
+
+    if available < required {
+        return Err(Error::PackageInsufficientSpace {
+            required,
+            available,
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn available_space(dir: &Path) -> Option<u64> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    let path = CString::new(dir.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+
+    #[allow(clippy::unnecessary_cast)]
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(windows)]
+fn available_space(dir: &Path) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let path: Vec<u16> = dir.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut available = 0u64;
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            path.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+
+    (ok != 0).then_some(available)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn available_space(_dir: &Path) -> Option<u64> {
+    None
+}
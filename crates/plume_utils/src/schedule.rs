@@ -0,0 +1,133 @@
+//! Ordering for signing nested bundles in parallel.
+//!
+//! A bundle's signature covers the signatures of everything nested in it, so a
+//! parent can only be signed once all of its children are. Bundles that don't
+//! contain each other (frameworks next to each other, separate extensions) are
+//! independent and get handed to worker threads as soon as they're ready.
+
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{Condvar, Mutex},
+    thread,
+};
+
+use crate::{Bundle, Error};
+
+pub(crate) struct SigningSchedule {
+    bundles: Vec<Bundle>,
+    /// Index of the nearest bundle containing each bundle.
+    parents: Vec<Option<usize>>,
+}
+
+struct State {
+    ready: Vec<usize>,
+    /// Children of each bundle that still have to be signed.
+    pending: Vec<usize>,
+    /// Bundles not signed yet, including the ones being signed.
+    remaining: usize,
+    error: Option<Error>,
+}
+
+impl SigningSchedule {
+    pub(crate) fn new(bundles: Vec<Bundle>) -> Self {
+        let parents = bundles
+            .iter()
+            .map(|bundle| {
+                bundles
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, other)| {
+                        other.bundle_dir() != bundle.bundle_dir()
+                            && bundle.bundle_dir().starts_with(other.bundle_dir())
+                    })
+                    .max_by_key(|(_, other)| other.bundle_dir().components().count())
+                    .map(|(index, _)| index)
+            })
+            .collect();
+
+        Self { bundles, parents }
+    }
+
+    /// Calls `sign` for every bundle on up to `threads` threads, children before their
+    /// parents. Stops handing out bundles after the first error and returns it, a
+    /// panic in `sign` counting as an error.
+    pub(crate) fn run<F>(&self, threads: usize, sign: F) -> Result<(), Error>
+    where
+        F: Fn(&Bundle) -> Result<(), Error> + Sync,
+    {
+        let mut pending = vec![0; self.bundles.len()];
+        for parent in self.parents.iter().flatten() {
+            pending[*parent] += 1;
+        }
+
+        // popped from the back, so the deepest bundles go first
+        let mut ready: Vec<usize> = (0..self.bundles.len())
+            .filter(|&index| pending[index] == 0)
+            .collect();
+        ready.sort_by_key(|&index| self.bundles[index].bundle_dir().components().count());
+
+        let state = Mutex::new(State {
+            ready,
+            pending,
+            remaining: self.bundles.len(),
+            error: None,
+        });
+        let wakeup = Condvar::new();
+
+        let worker = || {
+            loop {
+                let index = {
+                    let mut state = state.lock().unwrap();
+                    loop {
+                        if state.error.is_some() || state.remaining == 0 {
+                            return;
+                        }
+                        if let Some(index) = state.ready.pop() {
+                            break index;
+                        }
+                        state = wakeup.wait(state).unwrap();
+                    }
+                };
+
+                let bundle = &self.bundles[index];
+                let result =
+                    panic::catch_unwind(AssertUnwindSafe(|| sign(bundle))).unwrap_or_else(|_| {
+                        Err(Error::Other(format!(
+                            "Signing {} panicked",
+                            bundle.bundle_dir().display()
+                        )))
+                    });
+
+                let mut state = state.lock().unwrap();
+                state.remaining -= 1;
+                match result {
+                    Ok(()) => {
+                        if let Some(parent) = self.parents[index] {
+                            state.pending[parent] -= 1;
+                            if state.pending[parent] == 0 {
+                                state.ready.push(parent);
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        state.error.get_or_insert(e);
+                    }
+                }
+                wakeup.notify_all();
+            }
+        };
+
+        let threads = threads.clamp(1, self.bundles.len().max(1));
+        thread::scope(|scope| {
+            for _ in 1..threads {
+                scope.spawn(worker);
+            }
+            worker();
+        });
+
+        match state.into_inner().unwrap().error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
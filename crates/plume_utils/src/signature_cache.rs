@@ -0,0 +1,133 @@
+//! Remembers nested bundles we've already signed, so re-signing a stored app
+//! (e.g. on refresh) only redoes the ones that changed.
+//!
+//! Each bundle gets a small plist in the cache directory, named after a hash of
+//! its path, holding the identity it was signed with and a hash of its contents
+//! right after signing. The contents include the signature itself, so a match
+//! means the bundle is still exactly what we left behind.
+
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+use plist::{Dictionary, Value};
+use plume_core::CertificateIdentity;
+use sha2::{Digest, Sha256};
+
+use crate::{Bundle, BundleType, Error, SignerMode};
+
+pub(crate) struct SignatureCache {
+    dir: PathBuf,
+    identity: String,
+}
+
+impl SignatureCache {
+    pub(crate) fn new(
+        dir: PathBuf,
+        certificate: Option<&CertificateIdentity>,
+        mode: SignerMode,
+    ) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(mode.to_string());
+        if let Some(cert) = certificate.and_then(|c| c.cert.as_ref()) {
+            hasher.update(cert.constructed_data());
+        }
+
+        Self {
+            dir,
+            identity: format!("{:x}", hasher.finalize()),
+        }
+    }
+
+    /// Only bundles signed without entitlements qualify, apps and extensions get a
+    /// new profile every time.
+    pub(crate) fn applies_to(bundle: &Bundle) -> bool {
+        matches!(
+            bundle.bundle_type(),
+            BundleType::Framework | BundleType::Dylib
+        )
+    }
+
+    /// Whether the bundle was signed with the same identity and hasn't changed since.
+    pub(crate) fn is_signed(&self, bundle: &Bundle) -> bool {
+        let Ok(Value::Dictionary(entry)) = Value::from_file(self.entry_path(bundle)) else {
+            return false;
+        };
+
+        let field = |key| entry.get(key).and_then(Value::as_string);
+        if field("Identity") != Some(self.identity.as_str()) {
+            return false;
+        }
+
+        match content_hash(bundle.bundle_dir()) {
+            Ok(hash) => field("ContentHash") == Some(hash.as_str()),
+            Err(_) => false,
+        }
+    }
+
+    /// Records a freshly signed bundle.
+    pub(crate) fn record(&self, bundle: &Bundle) -> Result<(), Error> {
+        let mut entry = Dictionary::new();
+        entry.insert("Identity".into(), self.identity.clone().into());
+        entry.insert(
+            "ContentHash".into(),
+            content_hash(bundle.bundle_dir())?.into(),
+        );
+
+        fs::create_dir_all(&self.dir)?;
+        Value::Dictionary(entry).to_file_xml(self.entry_path(bundle))?;
+        Ok(())
+    }
+
+    fn entry_path(&self, bundle: &Bundle) -> PathBuf {
+        let path = bundle.bundle_dir().to_string_lossy();
+        self.dir
+            .join(format!("{:x}.plist", Sha256::digest(path.as_bytes())))
+    }
+}
+
+/// Hashes every file under `path` (or `path` itself for dylibs) along with its
+/// relative path, symlinks by their target.
+fn content_hash(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    hash_entry(path, Path::new(""), &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn hash_entry(path: &Path, relative: &Path, hasher: &mut Sha256) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(path)?;
+    hasher.update(relative.to_string_lossy().as_bytes());
+    hasher.update([0]);
+
+    if metadata.is_symlink() {
+        hasher.update(b"l");
+        hasher.update(fs::read_link(path)?.to_string_lossy().as_bytes());
+    } else if metadata.is_dir() {
+        hasher.update(b"d");
+        let mut entries = fs::read_dir(path)?
+            .map(|e| e.map(|e| e.file_name()))
+            .collect::<io::Result<Vec<_>>>()?;
+        entries.sort();
+
+        for name in entries {
+            hash_entry(&path.join(&name), &relative.join(&name), hasher)?;
+        }
+    } else {
+        hasher.update(b"f");
+        hasher.update(metadata.len().to_le_bytes());
+
+        let mut file = fs::File::open(path)?;
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            let read = file.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+    }
+
+    Ok(())
+}
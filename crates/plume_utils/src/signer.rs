@@ -1,20 +1,33 @@
 // TODO: move to plist macro
 use futures::future::try_join_all;
-use plist::Value;
-use std::sync::Arc;
+use plist::{Dictionary, Value};
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+    thread,
+    time::Instant,
+};
 use tokio::fs;
 
 use plume_core::{
-    CertificateIdentity, MobileProvision, SettingsScope, SigningSettings, UnifiedSigner,
+    CertificateIdentity, MachO, MobileProvision, SettingsScope, SigningSettings, UnifiedSigner,
     developer::DeveloperSession,
 };
 
-use crate::{Bundle, BundleType, Error, PlistInfoTrait, SignerApp, SignerMode, SignerOptions};
+use crate::{
+    Bundle, BundleType, CancellationToken, ComponentEntitlements, Error, PlistInfoTrait, SignerApp,
+    SignerMode, SignerOptions, SigningEventKind, SigningEvents, icon, reconcile_entitlements,
+    schedule::SigningSchedule, signature_cache::SignatureCache,
+};
 
 pub struct Signer {
     certificate: Option<CertificateIdentity>,
     pub options: SignerOptions,
     pub provisioning_files: Vec<MobileProvision>,
+    /// App IDs [`Signer::register_bundle`] added, see [`Signer::delete_created_app_ids`].
+    created_app_ids: Vec<String>,
+    events: SigningEvents,
+    cancel: CancellationToken,
 }
 
 impl Signer {
@@ -23,52 +36,67 @@ impl Signer {
             certificate,
             options,
             provisioning_files: Vec::new(),
+            created_app_ids: Vec::new(),
+            events: SigningEvents::default(),
+            cancel: CancellationToken::default(),
         }
     }
 
+    /// Reports progress of [`Signer::modify_bundle`], [`Signer::register_bundle`] and
+    /// [`Signer::sign_bundle`] to `events`.
+    pub fn with_events(mut self, events: SigningEvents) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Makes [`Signer::modify_bundle`], [`Signer::register_bundle`] and
+    /// [`Signer::sign_bundle`] stop with [`Error::Cancelled`] between steps once
+    /// `cancel` is triggered.
+    pub fn with_cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
     pub async fn modify_bundle(
         &mut self,
         bundle: &Bundle,
         team_id: &Option<String>,
     ) -> Result<(), Error> {
         if self.options.mode == SignerMode::None {
+            if let Err(e) = bundle.ensure_unencrypted() {
+                log::warn!("{e}");
+            }
             return Ok(());
         }
 
-        let bundles = bundle
-            .collect_bundles_sorted()?
-            .into_iter()
-            .filter(|b| b.bundle_type().should_have_entitlements())
-            .collect::<Vec<_>>();
+        self.cancel.check()?;
 
-        if let Some(new_name) = self.options.custom_name.as_ref() {
-            bundle.set_name(new_name)?;
+        // before anything else, so removed components are never checked or registered
+        if !self.options.remove_components.is_empty() {
+            bundle.remove_components(&self.options.remove_components)?;
         }
 
-        if let Some(new_version) = self.options.custom_version.as_ref() {
-            bundle.set_version(new_version)?;
+        if let Some(keep) = self.options.keep_localizations.as_ref() {
+            bundle.remove_localizations(keep)?;
         }
 
-        if self.options.features.support_minimum_os_version {
-            bundle.set_info_plist_key("MinimumOSVersion", "7.0")?;
-        }
+        bundle.ensure_unencrypted()?;
 
-        if self.options.features.support_file_sharing {
-            bundle.set_info_plist_key("UIFileSharingEnabled", true)?;
-            bundle.set_info_plist_key("UISupportsDocumentBrowser", true)?;
-        }
+        // before identifiers are changed, patches target extensions by their original ones
+        let mut patches = self.options.features.info_plist_patches();
+        patches.extend(self.options.info_plist_patches.iter().cloned());
+        bundle.apply_info_plist_patches(&patches)?;
 
-        if self.options.features.support_ipad_fullscreen {
-            bundle.set_info_plist_key("UIRequiresFullScreen", true)?;
+        // nothing left to rename when `remove_url_schemes` dropped them above
+        if let Some(rewrite) = self.options.url_scheme_rewrite.as_ref() {
+            bundle.rewrite_url_schemes(rewrite)?;
         }
 
-        if self.options.features.support_game_mode {
-            bundle.set_info_plist_key("GCSupportsGameMode", true)?;
-        }
-
-        if self.options.features.support_pro_motion {
-            bundle.set_info_plist_key("CADisableMinimumFrameDurationOnPhone", true)?;
-        }
+        let bundles = bundle
+            .collect_bundles_sorted()?
+            .into_iter()
+            .filter(|b| b.bundle_type().should_have_entitlements())
+            .collect::<Vec<_>>();
 
         let identifier = bundle.get_bundle_identifier();
 
@@ -78,6 +106,7 @@ impl Signer {
             }
         }
 
+        // nested bundles are edited on their own, before the main Info.plist is opened below
         if let Some(new_identifier) = self.options.custom_identifier.as_ref() {
             if let Some(orig_identifier) = identifier {
                 for embedded_bundle in &bundles {
@@ -86,6 +115,22 @@ impl Signer {
             }
         }
 
+        let mut info = bundle.edit_info_plist()?;
+
+        if let Some(new_name) = self.options.custom_name.as_ref() {
+            info.set("CFBundleDisplayName", new_name.as_str())
+                .set("CFBundleName", new_name.as_str());
+        }
+
+        if let Some(new_version) = self.options.custom_version.as_ref() {
+            info.set("CFBundleShortVersionString", new_version.as_str())
+                .set("CFBundleVersion", new_version.as_str());
+        }
+
+        if self.options.features.support_minimum_os_version {
+            info.set("MinimumOSVersion", "7.0");
+        }
+
         if self.options.app == SignerApp::SideStore
             || self.options.app == SignerApp::AltStore
             || self.options.app == SignerApp::LiveContainerAndSideStore
@@ -115,7 +160,7 @@ impl Signer {
                             }
                         }
                         SignerApp::SideStore | SignerApp::AltStore => {
-                            bundle.set_info_plist_key("ALTCertificateID", &**serial_number)?;
+                            info.set("ALTCertificateID", serial_number.as_str());
                             fs::write(bundle.bundle_dir().join("ALTCertificate.p12"), p12_data)
                                 .await?;
                         }
@@ -126,63 +171,40 @@ impl Signer {
         }
 
         if let Some(custom_icon) = &self.options.custom_icon {
-            let image_sizes: &[(&str, u32)] = &[
-                ("FRIcon60x60@2x.png", 120),
-                ("FRIcon76x76@2x~ipad.png", 152),
-            ];
+            let files = icon::write_icon_set(custom_icon, bundle.bundle_dir(), "FRIcon")?;
 
-            let img = image::open(custom_icon)?;
+            // replacing the primary icon drops its CFBundleIconName, so the asset
+            // catalog icon no longer takes precedence
+            for (key, ipad) in [("CFBundleIcons", false), ("CFBundleIcons~ipad", true)] {
+                info.set_path(&[key, "CFBundlePrimaryIcon"], files.icon_entry(ipad));
+            }
+            info.remove("CFBundleIconName");
+        }
 
-            for &(file_name, size) in image_sizes {
-                let filled = img.resize_to_fill(size, size, image::imageops::FilterType::Lanczos3);
+        for (name, image) in &self.options.custom_alternate_icons {
+            let prefix = format!("FRAlt{}", icon::file_name_prefix(name));
+            let files = icon::write_icon_set(image, bundle.bundle_dir(), &prefix)?;
 
-                let out_path = bundle.bundle_dir().join(file_name);
-                filled.save_with_format(&out_path, image::ImageFormat::Png)?;
+            for (key, ipad) in [("CFBundleIcons", false), ("CFBundleIcons~ipad", true)] {
+                info.set_path(
+                    &[key, "CFBundleAlternateIcons", name.as_str()],
+                    files.icon_entry(ipad),
+                );
             }
+        }
 
-            let cf_bundle_icons = Value::Dictionary({
-                let mut primary = plist::Dictionary::new();
-                primary.insert(
-                    "CFBundleIconFiles".to_string(),
-                    Value::Array(vec![Value::String("FRIcon60x60".to_string())]),
-                );
-                primary.insert(
-                    "CFBundleIconName".to_string(),
-                    Value::String("FRIcon".to_string()),
-                );
-                let mut d = plist::Dictionary::new();
-                d.insert(
-                    "CFBundlePrimaryIcon".to_string(),
-                    Value::Dictionary(primary),
-                );
-                d
-            });
-
-            let cf_bundle_icons_ipad = Value::Dictionary({
-                let mut primary = plist::Dictionary::new();
-                primary.insert(
-                    "CFBundleIconFiles".to_string(),
-                    Value::Array(vec![
-                        Value::String("FRIcon60x60".to_string()),
-                        Value::String("FRIcon76x76".to_string()),
-                    ]),
-                );
-                primary.insert(
-                    "CFBundleIconName".to_string(),
-                    Value::String("FRIcon".to_string()),
-                );
-                let mut d = plist::Dictionary::new();
-                d.insert(
-                    "CFBundlePrimaryIcon".to_string(),
-                    Value::Dictionary(primary),
-                );
-                d
-            });
+        info.save()?;
+
+        if let Some(new_name) = self.options.custom_name.as_ref() {
+            bundle.rename_localizations(new_name)?;
+        }
 
-            bundle.set_info_plist_key("CFBundleIcons", cf_bundle_icons)?;
-            bundle.set_info_plist_key("CFBundleIcons~ipad", cf_bundle_icons_ipad)?;
+        if !self.options.custom_localized_names.is_empty() {
+            bundle.set_localized_names(&self.options.custom_localized_names)?;
         }
 
+        self.cancel.check()?;
+
         let has_tweaks = self.options.tweaks.as_ref().is_some_and(|t| !t.is_empty());
 
         if self.options.features.support_ellekit || has_tweaks {
@@ -191,14 +213,20 @@ impl Signer {
 
         if let Some(tweak_files) = self.options.tweaks.as_ref() {
             for tweak_file in tweak_files {
+                self.cancel.check()?;
+                let started = Instant::now();
                 let tweak = crate::Tweak::new(tweak_file, bundle).await?;
                 tweak.apply().await?;
+                self.events.emit_since(
+                    started,
+                    SigningEventKind::TweakApplied {
+                        tweak: tweak_file.clone(),
+                    },
+                );
             }
         }
 
         if self.options.features.support_liquid_glass {
-            bundle.set_info_plist_key("UIDesignRequiresCompatibility", false)?;
-
             let executable_name = bundle
                 .get_executable()
                 .ok_or(Error::BundleInfoPlistMissing)?;
@@ -212,6 +240,36 @@ impl Signer {
             macho.replace_sdk_version("26.0.0")?;
         }
 
+        // done last so tweaks injected above are covered as well
+        if let Some(minimum_os_version) = self.options.minimum_os_version.as_ref() {
+            Self::apply_minimum_os_version(bundle, minimum_os_version)?;
+        }
+
+        self.cancel.check()?;
+
+        // dyld aborts on launch for these, so catch them before signing
+        bundle.warn_unresolved_dependencies()?;
+
+        Ok(())
+    }
+
+    /// Sets `MinimumOSVersion` in every nested Info.plist and the minimum OS of every
+    /// binary's `LC_BUILD_VERSION` / `LC_VERSION_MIN_IPHONEOS`, so they agree.
+    fn apply_minimum_os_version(bundle: &Bundle, version: &str) -> Result<(), Error> {
+        for sub_bundle in bundle.collect_bundles_sorted()? {
+            if *sub_bundle.bundle_type() == BundleType::Unknown {
+                continue;
+            }
+
+            if *sub_bundle.bundle_type() != BundleType::Dylib {
+                sub_bundle.set_info_plist_key("MinimumOSVersion", version)?;
+            }
+
+            if let Some(binary_path) = sub_bundle.executable_path() {
+                plume_core::MachO::new(&binary_path)?.set_minimum_os_version(version)?;
+            }
+        }
+
         Ok(())
     }
 
@@ -226,6 +284,8 @@ impl Signer {
             return Ok(());
         }
 
+        self.cancel.check()?;
+
         let bundles = bundle
             .collect_bundles_sorted()?
             .into_iter()
@@ -236,6 +296,8 @@ impl Signer {
         let bundle_arc = Arc::new(bundle.clone());
         let session_arc = Arc::new(session);
         let team_id_arc = Arc::new(team_id.clone());
+        // App IDs added by this call, deleted again if it gets cancelled
+        let created_app_ids = Mutex::new(Vec::new());
 
         let futures = bundles.iter().filter_map(|sub_bundle| {
             let sub_bundle = sub_bundle.clone();
@@ -243,6 +305,9 @@ impl Signer {
             let session = session_arc.clone();
             let team_id = team_id_arc.clone();
             let signer_settings = signer_settings.clone();
+            let events = self.events.clone();
+            let cancel = self.cancel.clone();
+            let created_app_ids = &created_app_ids;
 
             if signer_settings.embedding.single_profile
                 && sub_bundle.bundle_dir() != bundle.bundle_dir()
@@ -269,17 +334,37 @@ impl Signer {
 
                 let name = sub_bundle.get_bundle_name().unwrap_or_else(|| id.clone());
 
-                session.qh_ensure_app_id(&team_id, &name, &id).await?;
+                cancel.check()?;
+                let started = Instant::now();
+                if session.qh_get_app_id(&team_id, &id).await?.is_none() {
+                    let added = session.qh_add_app_id(&team_id, &name, &id).await?;
+                    created_app_ids.lock().unwrap().push(added.app_id.app_id_id);
+                }
 
                 let app_id_id = session
                     .qh_get_app_id(&team_id, &id)
                     .await?
                     .ok_or_else(|| Error::Other("Failed to get ensured app ID.".into()))?;
+                events.emit_since(
+                    started,
+                    SigningEventKind::AppIdEnsured {
+                        identifier: id.clone(),
+                    },
+                );
+
+                cancel.check()?;
 
                 if let Some(e) = macho.entitlements().as_ref() {
+                    let started = Instant::now();
                     session
                         .v1_request_capabilities_for_entitlements(&team_id, &id, e)
                         .await?;
+                    events.emit_since(
+                        started,
+                        SigningEventKind::CapabilitiesEnabled {
+                            identifier: id.clone(),
+                        },
+                    );
                 }
 
                 if let Some(app_groups) = macho.app_groups_for_entitlements() {
@@ -316,6 +401,8 @@ impl Signer {
                         .await?;
                 }
 
+                cancel.check()?;
+                let started = Instant::now();
                 let profiles = session
                     .qh_get_profile(&team_id, &app_id_id.app_id_id)
                     .await?;
@@ -328,24 +415,64 @@ impl Signer {
                 .await?;
                 let mobile_provision =
                     MobileProvision::load_with_bytes(profile_data.as_ref().to_vec())?;
+                events.emit_since(
+                    started,
+                    SigningEventKind::ProfileDownloaded { identifier: id },
+                );
                 Ok::<_, Error>(mobile_provision)
             })
         });
 
-        let provisionings: Vec<MobileProvision> = try_join_all(futures).await?;
+        let result = try_join_all(futures).await;
+        self.created_app_ids
+            .extend(created_app_ids.into_inner().unwrap_or_default());
+        let provisionings: Vec<MobileProvision> = match result {
+            Ok(provisionings) => provisionings,
+            Err(Error::Cancelled) => {
+                self.delete_created_app_ids(session, team_id).await;
+                return Err(Error::Cancelled);
+            }
+            Err(e) => return Err(e),
+        };
         self.provisioning_files = provisionings;
 
         Ok(())
     }
 
+    /// Deletes the App IDs [`Signer::register_bundle`] added, for runs cancelled after
+    /// registering so they don't count against the account's limit. Failures are
+    /// only logged.
+    pub async fn delete_created_app_ids(&mut self, session: &DeveloperSession, team_id: &String) {
+        for app_id_id in self.created_app_ids.drain(..) {
+            if let Err(e) = session.qh_delete_app_id(team_id, &app_id_id).await {
+                log::warn!("Failed to delete App ID {app_id_id}: {e}");
+            }
+        }
+    }
+
+    /// Signs the bundle and everything nested in it. Bundles that don't contain each
+    /// other are signed in parallel, each one only after everything inside it.
     pub async fn sign_bundle(&self, bundle: &Bundle) -> Result<(), Error> {
         if self.options.mode == SignerMode::None {
             return Ok(());
         }
 
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        self.sign_bundles(bundle, threads)
+    }
+
+    fn sign_bundles(&self, bundle: &Bundle, threads: usize) -> Result<(), Error> {
+        let root = bundle.bundle_dir();
         let bundles = bundle.collect_bundles_sorted()?;
+        for bundle in &bundles {
+            if *bundle.bundle_type() != BundleType::Unknown {
+                self.events.emit(SigningEventKind::BundleDiscovered {
+                    bundle: bundle.bundle_dir().clone(),
+                    bundle_type: bundle.bundle_type().clone(),
+                });
+            }
+        }
 
-        let settings = Self::build_base_settings(self.certificate.as_ref())?;
         let entitlements_xml = r#"<?xml version="1.0" encoding="UTF-8"?>
 <!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
 <plist version="1.0">
@@ -353,16 +480,62 @@ impl Signer {
 </plist>
 "#.to_string();
 
-        for bundle in &bundles {
+        let cache = self
+            .options
+            .signature_cache
+            .clone()
+            .map(|dir| SignatureCache::new(dir, self.certificate.as_ref(), self.options.mode));
+
+        SigningSchedule::new(bundles).run(threads, |bundle| {
+            self.cancel.check()?;
+
+            // the cache doesn't know about overrides, sign those every time
+            let component_entitlements = self.component_entitlements(root, bundle);
+            let cache = cache
+                .as_ref()
+                .filter(|_| SignatureCache::applies_to(bundle) && component_entitlements.is_none());
+
+            if cache.is_some_and(|cache| cache.is_signed(bundle)) {
+                log::info!("Already signed: {}", bundle.bundle_dir().display());
+                self.events.emit(SigningEventKind::BundleSigned {
+                    bundle: bundle.bundle_dir().clone(),
+                    cached: true,
+                });
+                return Ok(());
+            }
+
             log::info!("Signing bundle: {}", bundle.bundle_dir().display());
+            let started = Instant::now();
+            // the settings borrow the key as a `dyn KeyInfoSigner`, which can't be
+            // shared between threads, so every bundle builds its own
+            let settings = Self::build_base_settings(self.certificate.as_ref())?;
             Self::sign_single_bundle(
                 self,
                 bundle,
                 &self.provisioning_files,
-                settings.clone(),
+                settings,
                 &entitlements_xml,
+                component_entitlements,
             )?;
-        }
+
+            if *bundle.bundle_type() != BundleType::Unknown {
+                self.events.emit_since(
+                    started,
+                    SigningEventKind::BundleSigned {
+                        bundle: bundle.bundle_dir().clone(),
+                        cached: false,
+                    },
+                );
+            }
+
+            if let Some(cache) = cache {
+                if let Err(e) = cache.record(bundle) {
+                    log::warn!("Failed to cache signature: {e}");
+                }
+            }
+
+            Ok(())
+        })?;
 
         if let Some(cert) = &self.certificate {
             if let Some(key) = &cert.key {
@@ -379,12 +552,17 @@ impl Signer {
         provisioning_files: &[MobileProvision],
         mut settings: SigningSettings<'_>,
         entitlements_xml: &String,
+        component_entitlements: Option<&ComponentEntitlements>,
     ) -> Result<(), Error> {
         if *bundle.bundle_type() == BundleType::Unknown {
             return Ok(());
         }
 
         let mut entitlements_xml = entitlements_xml.clone();
+        let mut component_entitlements = component_entitlements;
+        let custom_entitlements = self.options.custom_entitlements.as_ref().filter(|_| {
+            self.options.mode != SignerMode::Adhoc && self.options.embedding.single_profile
+        });
 
         // Only Apps and AppExtensions should have entitlements from provisioning profiles
         // Dylibs, frameworks, and other components should be signed without entitlements
@@ -407,13 +585,29 @@ impl Signer {
             }
 
             if let Some(prov) = matched_prov.or_else(|| provisioning_files.first()) {
-                let mut prov = prov.clone();
+                let mut requested = bundle
+                    .executable_path()
+                    .and_then(|path| MachO::new(path).ok())
+                    .and_then(|macho| macho.entitlements().clone())
+                    .unwrap_or_default();
+                // overrides go through the same checks, the profile has to grant them too,
+                // unless custom entitlements replace all of it below anyway
+                let overrides = component_entitlements.take_if(|_| custom_entitlements.is_none());
+                if let Some(component_entitlements) = overrides {
+                    requested = apply_component_entitlements(requested, component_entitlements)?;
+                }
+                let bundle_id = bundle.get_bundle_identifier().unwrap_or_default();
 
-                if let Some(bundle_executable) = bundle.get_executable() {
-                    if let Some(bundle_id) = bundle.get_bundle_identifier() {
-                        let binary_path = bundle.bundle_dir().join(bundle_executable);
-                        prov.merge_entitlements(binary_path, &bundle_id).ok();
-                    }
+                let (entitlements, changes) =
+                    reconcile_entitlements(&requested, prov.entitlements(), &bundle_id);
+                for change in &changes {
+                    log::warn!("{}: {change}", bundle.bundle_dir().display());
+                }
+                if !changes.is_empty() {
+                    self.events.emit(SigningEventKind::EntitlementsReconciled {
+                        bundle: bundle.bundle_dir().clone(),
+                        changes,
+                    });
                 }
 
                 std::fs::write(
@@ -421,19 +615,38 @@ impl Signer {
                     &prov.data,
                 )?;
 
-                if let Ok(ent_xml) = prov.entitlements_as_bytes() {
-                    entitlements_xml = String::from_utf8_lossy(&ent_xml).to_string();
-                }
+                let mut ent_xml = Vec::new();
+                Value::Dictionary(entitlements).to_writer_xml(&mut ent_xml)?;
+                entitlements_xml = String::from_utf8_lossy(&ent_xml).to_string();
             }
         }
 
-        if self.options.mode != SignerMode::Adhoc {
-            if self.options.embedding.single_profile {
-                if let Some(ent_path) = &self.options.custom_entitlements {
-                    let ent_bytes = std::fs::read(ent_path)?;
-                    entitlements_xml = String::from_utf8_lossy(&ent_bytes).to_string();
-                }
-            }
+        if let Some(ent_path) = custom_entitlements {
+            let ent_bytes = std::fs::read(ent_path)?;
+            entitlements_xml = String::from_utf8_lossy(&ent_bytes).to_string();
+        }
+
+        if let Some(component_entitlements) = component_entitlements {
+            // ad-hoc signatures keep whatever the binary was signed with before
+            let base = if self.options.mode == SignerMode::Adhoc {
+                bundle
+                    .executable_path()
+                    .and_then(|path| MachO::new(path).ok())
+                    .and_then(|macho| macho.entitlements().clone())
+                    .unwrap_or_default()
+            } else {
+                Value::from_reader_xml(entitlements_xml.as_bytes())?
+                    .into_dictionary()
+                    .unwrap_or_default()
+            };
+
+            let mut xml = Vec::new();
+            Value::Dictionary(apply_component_entitlements(base, component_entitlements)?)
+                .to_writer_xml(&mut xml)?;
+            entitlements_xml = String::from_utf8_lossy(&xml).into_owned();
+        }
+
+        if self.options.mode != SignerMode::Adhoc || component_entitlements.is_some() {
             settings.set_entitlements_xml(SettingsScope::Main, entitlements_xml)?;
         }
 
@@ -442,6 +655,30 @@ impl Signer {
         Ok(())
     }
 
+    /// Override for `bundle` from [`SignerOptions::component_entitlements`], looked up
+    /// by its path relative to `root` first, then by its bundle identifier.
+    fn component_entitlements(
+        &self,
+        root: &Path,
+        bundle: &Bundle,
+    ) -> Option<&ComponentEntitlements> {
+        let overrides = &self.options.component_entitlements;
+        if overrides.is_empty() {
+            return None;
+        }
+
+        let relative = bundle.bundle_dir().strip_prefix(root).ok()?;
+        let relative = if relative.as_os_str().is_empty() {
+            ".".to_string()
+        } else {
+            relative.to_string_lossy().replace('\\', "/")
+        };
+
+        overrides
+            .get(&relative)
+            .or_else(|| overrides.get(&bundle.get_bundle_identifier()?))
+    }
+
     fn build_base_settings(
         certificate: Option<&CertificateIdentity>,
     ) -> Result<SigningSettings<'_>, Error> {
@@ -457,3 +694,114 @@ impl Signer {
         Ok(settings)
     }
 }
+
+/// Entitlements for a component with an override, `base` being what it would be
+/// signed with otherwise.
+fn apply_component_entitlements(
+    mut base: Dictionary,
+    component_entitlements: &ComponentEntitlements,
+) -> Result<Dictionary, Error> {
+    let path = &component_entitlements.path;
+    let overrides = Value::from_file(path)?
+        .into_dictionary()
+        .ok_or_else(|| Error::Other(format!("{} is not an entitlements plist", path.display())))?;
+
+    if component_entitlements.replace {
+        Ok(overrides)
+    } else {
+        base.extend(overrides);
+        Ok(base)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, env, fs, path::PathBuf};
+
+    use apple_codesign::macho_builder::MachOBuilder;
+    use goblin::mach::header::{MH_DYLIB, MH_EXECUTE};
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn write_bundle(dir: &Path, identifier: &str, file_type: u32) {
+        fs::create_dir_all(dir).unwrap();
+        let name = dir.file_stem().unwrap().to_str().unwrap();
+
+        let mut info = Dictionary::new();
+        info.insert("CFBundleExecutable".into(), name.into());
+        info.insert("CFBundleIdentifier".into(), identifier.into());
+        Value::Dictionary(info)
+            .to_file_xml(dir.join("Info.plist"))
+            .unwrap();
+
+        let binary = MachOBuilder::new_aarch64(file_type).write_macho().unwrap();
+        fs::write(dir.join(name), binary).unwrap();
+    }
+
+    /// An app with frameworks next to each other and an extension with its own.
+    fn write_app(dir: &Path) -> Bundle {
+        let app = dir.join("Example.app");
+        write_bundle(&app, "com.example.app", MH_EXECUTE);
+        for name in ["A", "B", "C"] {
+            let framework = app.join("Frameworks").join(format!("{name}.framework"));
+            write_bundle(&framework, &format!("com.example.{name}"), MH_DYLIB);
+        }
+
+        let widget = app.join("PlugIns/Widget.appex");
+        write_bundle(&widget, "com.example.app.widget", MH_EXECUTE);
+        write_bundle(
+            &widget.join("Frameworks/D.framework"),
+            "com.example.D",
+            MH_DYLIB,
+        );
+
+        Bundle::new(app).unwrap()
+    }
+
+    fn read_tree(dir: &Path, root: &Path, files: &mut BTreeMap<PathBuf, Vec<u8>>) {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                read_tree(&path, root, files);
+            } else {
+                let relative = path.strip_prefix(root).unwrap().to_path_buf();
+                files.insert(relative, fs::read(&path).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn parallel_signing_matches_serial() {
+        let dir = env::temp_dir().join(format!("plume_signer_{}", Uuid::new_v4()));
+
+        let signed: Vec<_> = [1, 8]
+            .into_iter()
+            .map(|threads| {
+                let bundle = write_app(&dir.join(threads.to_string()));
+                let signer = Signer::new(
+                    None,
+                    SignerOptions {
+                        mode: SignerMode::Adhoc,
+                        ..SignerOptions::default()
+                    },
+                );
+                signer.sign_bundles(&bundle, threads).unwrap();
+
+                let mut files = BTreeMap::new();
+                read_tree(bundle.bundle_dir(), bundle.bundle_dir(), &mut files);
+                files
+            })
+            .collect();
+
+        fs::remove_dir_all(&dir).ok();
+
+        // every binary and CodeResources, so the CodeDirectory hashes of each bundle
+        assert!(
+            signed[0]
+                .keys()
+                .any(|path| path.ends_with("_CodeSignature/CodeResources"))
+        );
+        assert_eq!(signed[0], signed[1]);
+    }
+}
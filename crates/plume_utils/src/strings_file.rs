@@ -0,0 +1,261 @@
+//! Editing `.strings` files (e.g. `InfoPlist.strings`) without changing their format.
+//!
+//! Xcode compiles them to binary plists, but hand made or older apps ship the
+//! text format, usually UTF-16 with a BOM. Text files are edited in place so
+//! comments, ordering and encoding survive.
+
+use std::{fs, path::Path};
+
+use plist::Value;
+
+use crate::Error;
+
+/// Sets every `keys` entry already present to `value`. With `insert_missing`, the
+/// first key is added when none of them exist (creating the file if needed).
+pub(crate) fn set_values(
+    path: &Path,
+    keys: &[&str],
+    value: &str,
+    insert_missing: bool,
+) -> Result<(), Error> {
+    if !path.exists() {
+        if insert_missing {
+            let contents = format!("{} = {};\n", quote(keys[0]), quote(value));
+            fs::write(path, contents)?;
+        }
+        return Ok(());
+    }
+
+    let data = fs::read(path)?;
+
+    if data.starts_with(b"bplist") || is_xml(&data) {
+        return set_plist_values(path, &data, keys, value, insert_missing);
+    }
+
+    let (text, encoding) = decode(&data);
+    let mut edits: Vec<(usize, usize)> = parse_entries(&text)
+        .into_iter()
+        .filter(|entry| keys.contains(&entry.key.as_str()))
+        .map(|entry| entry.value_span)
+        .collect();
+    edits.sort_by_key(|(start, _)| std::cmp::Reverse(*start));
+
+    let found = !edits.is_empty();
+    let mut text = text;
+    for (start, end) in edits {
+        text.replace_range(start..end, &quote(value));
+    }
+
+    if !found && insert_missing {
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        text.push_str(&format!("{} = {};\n", quote(keys[0]), quote(value)));
+    } else if !found {
+        return Ok(());
+    }
+
+    fs::write(path, encode(&text, encoding))?;
+    Ok(())
+}
+
+fn set_plist_values(
+    path: &Path,
+    data: &[u8],
+    keys: &[&str],
+    value: &str,
+    insert_missing: bool,
+) -> Result<(), Error> {
+    let mut plist = Value::from_reader(std::io::Cursor::new(data))?;
+    let Some(dict) = plist.as_dictionary_mut() else {
+        return Ok(());
+    };
+
+    let mut found = false;
+    for key in keys {
+        if dict.contains_key(key) {
+            dict.insert(key.to_string(), Value::String(value.to_string()));
+            found = true;
+        }
+    }
+
+    if !found {
+        if !insert_missing {
+            return Ok(());
+        }
+        dict.insert(keys[0].to_string(), Value::String(value.to_string()));
+    }
+
+    if data.starts_with(b"bplist") {
+        plist.to_file_binary(path)?;
+    } else {
+        plist.to_file_xml(path)?;
+    }
+
+    Ok(())
+}
+
+fn is_xml(data: &[u8]) -> bool {
+    let data = data.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(data);
+    let start = data
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(data.len());
+    data[start..].starts_with(b"<?xml") || data[start..].starts_with(b"<plist")
+}
+
+#[derive(Clone, Copy)]
+enum Encoding {
+    Utf8 { bom: bool },
+    Utf16Le { bom: bool },
+    Utf16Be,
+}
+
+fn decode(data: &[u8]) -> (String, Encoding) {
+    let utf16 = |bytes: &[u8], from: fn([u8; 2]) -> u16| {
+        let units: Vec<u16> = bytes.chunks_exact(2).map(|c| from([c[0], c[1]])).collect();
+        String::from_utf16_lossy(&units)
+    };
+
+    if let Some(rest) = data.strip_prefix(b"\xFF\xFE") {
+        (
+            utf16(rest, u16::from_le_bytes),
+            Encoding::Utf16Le { bom: true },
+        )
+    } else if let Some(rest) = data.strip_prefix(b"\xFE\xFF") {
+        (utf16(rest, u16::from_be_bytes), Encoding::Utf16Be)
+    } else if let Some(rest) = data.strip_prefix(b"\xEF\xBB\xBF") {
+        (
+            String::from_utf8_lossy(rest).into_owned(),
+            Encoding::Utf8 { bom: true },
+        )
+    } else if data.len() >= 2 && data[1] == 0 {
+        // BOM-less UTF-16, ASCII keys leave every other byte zero
+        (
+            utf16(data, u16::from_le_bytes),
+            Encoding::Utf16Le { bom: false },
+        )
+    } else {
+        (
+            String::from_utf8_lossy(data).into_owned(),
+            Encoding::Utf8 { bom: false },
+        )
+    }
+}
+
+fn encode(text: &str, encoding: Encoding) -> Vec<u8> {
+    match encoding {
+        Encoding::Utf8 { bom } => {
+            let mut out = if bom {
+                b"\xEF\xBB\xBF".to_vec()
+            } else {
+                Vec::new()
+            };
+            out.extend_from_slice(text.as_bytes());
+            out
+        }
+        Encoding::Utf16Le { bom } => {
+            let mut out = if bom {
+                b"\xFF\xFE".to_vec()
+            } else {
+                Vec::new()
+            };
+            out.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+            out
+        }
+        Encoding::Utf16Be => {
+            let mut out = b"\xFE\xFF".to_vec();
+            out.extend(text.encode_utf16().flat_map(u16::to_be_bytes));
+            out
+        }
+    }
+}
+
+struct Entry {
+    key: String,
+    /// Byte range of the value token, quotes included.
+    value_span: (usize, usize),
+}
+
+/// Collects `key = value;` pairs, skipping comments.
+fn parse_entries(text: &str) -> Vec<Entry> {
+    let bytes = text.as_bytes();
+    let mut entries = Vec::new();
+    let mut tokens: Vec<(String, usize, usize)> = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i += 2;
+                while i + 1 < bytes.len() && !(bytes[i] == b'*' && bytes[i + 1] == b'/') {
+                    i += 1;
+                }
+                i += 2;
+            }
+            b'"' => {
+                let start = i;
+                i += 1;
+                let mut value = String::new();
+                while i < bytes.len() && bytes[i] != b'"' {
+                    if bytes[i] == b'\\' && i + 1 < bytes.len() {
+                        i += 1;
+                    }
+                    let ch = text[i..].chars().next().unwrap_or_default();
+                    value.push(ch);
+                    i += ch.len_utf8();
+                }
+                i += 1;
+                tokens.push((value, start, i.min(bytes.len())));
+            }
+            b';' => {
+                // key = value ;
+                match tokens.as_slice() {
+                    [(key, ..), (eq, ..), (_, start, end)] if eq == "=" => {
+                        entries.push(Entry {
+                            key: key.clone(),
+                            value_span: (*start, *end),
+                        });
+                    }
+                    _ => {}
+                }
+                tokens.clear();
+                i += 1;
+            }
+            b'=' => {
+                tokens.push(("=".to_string(), i, i + 1));
+                i += 1;
+            }
+            b if b.is_ascii_whitespace() => i += 1,
+            _ => {
+                // unquoted token
+                let start = i;
+                while i < bytes.len()
+                    && !bytes[i].is_ascii_whitespace()
+                    && !matches!(bytes[i], b'=' | b';' | b'"')
+                {
+                    i += 1;
+                }
+                if i == start {
+                    i += 1;
+                }
+                tokens.push((text[start..i].to_string(), start, i));
+            }
+        }
+    }
+
+    entries
+}
+
+fn quote(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{escaped}\"")
+}
@@ -24,12 +24,14 @@ impl Tweak {
         let deb_path = stage_dir.join("ellekit.deb");
         tokio::fs::write(&deb_path, ELLEKIT_BYTES).await?;
 
-        let tweak = Tweak::new(&deb_path, app_bundle).await?;
-        tweak.install_deb().await?;
+        let result = match Tweak::new(&deb_path, app_bundle).await {
+            Ok(tweak) => tweak.apply().await,
+            Err(e) => Err(e),
+        };
 
         tokio::fs::remove_dir_all(&stage_dir).await.ok();
 
-        Ok(())
+        result
     }
 
     pub async fn new<P: AsRef<Path>>(tweak_path: P, app_bundle: &Bundle) -> Result<Self, Error> {
@@ -62,7 +64,14 @@ impl Tweak {
         })
     }
 
+    /// Installs the tweak into the app, removing the stage directory even if it fails.
     pub async fn apply(&self) -> Result<(), Error> {
+        let result = self.install().await;
+        tokio::fs::remove_dir_all(&self.stage_dir).await.ok();
+        result
+    }
+
+    async fn install(&self) -> Result<(), Error> {
         let file_name = self
             .path
             .file_name()
@@ -81,8 +90,6 @@ impl Tweak {
             self.install_dylib(&self.path).await?;
         }
 
-        tokio::fs::remove_dir_all(&self.stage_dir).await.ok();
-
         Ok(())
     }
 
@@ -225,8 +232,10 @@ impl Tweak {
 
         tokio::fs::copy(dylib_path, &dest).await?;
 
+        let install_name = Self::rpath_install_name(&dest, false)?;
         Self::patch_cydiasubstrate(&dest);
-        self.inject_dylib(&dest, false).await
+        Self::normalize_install_name(&dest, &install_name)?;
+        self.inject_dylib(&install_name).await
     }
 
     async fn install_framework(&self, framework_path: &Path) -> Result<(), Error> {
@@ -242,8 +251,10 @@ impl Tweak {
             if let Some(exec_name) = bundle.get_executable() {
                 let exec_path = dest.join(exec_name);
                 if exec_path.exists() {
+                    let install_name = Self::rpath_install_name(&exec_path, true)?;
                     Self::patch_cydiasubstrate(&exec_path);
-                    self.inject_dylib(&exec_path, true).await?;
+                    Self::normalize_install_name(&exec_path, &install_name)?;
+                    self.inject_dylib(&install_name).await?;
                 }
             }
         }
@@ -268,7 +279,7 @@ impl Tweak {
         copy_dir_recursively(appex_path, &dest).await
     }
 
-    async fn inject_dylib(&self, dylib_path: &Path, is_framework: bool) -> Result<(), Error> {
+    async fn inject_dylib(&self, inject_path: &str) -> Result<(), Error> {
         let bundle = Bundle::new(&self.app_bundle)?;
         let executable_name = bundle
             .get_executable()
@@ -279,8 +290,20 @@ impl Tweak {
             return Err(Error::BundleInfoPlistMissing);
         }
 
-        let inject_path = if is_framework {
-            let components: Vec<_> = dylib_path.components().rev().take(2).collect();
+        let mut macho = MachO::new(&executable_path)?;
+        macho.add_dylib(inject_path)?;
+        // injected binaries live in Frameworks, make sure @rpath can find them
+        macho.add_rpath("@executable_path/Frameworks")?;
+        macho.write_changes()?;
+
+        Ok(())
+    }
+
+    /// Returns the `@rpath` install name a binary copied into `Frameworks` should have,
+    /// `@rpath/<name>.dylib` or `@rpath/<name>.framework/<name>`.
+    fn rpath_install_name(binary_path: &Path, is_framework: bool) -> Result<String, Error> {
+        let install_name = if is_framework {
+            let components: Vec<_> = binary_path.components().rev().take(2).collect();
             format!(
                 "@rpath/{}/{}",
                 components[1]
@@ -293,16 +316,21 @@ impl Tweak {
                     .ok_or(Error::TweakInvalidPath)?
             )
         } else {
-            let file_name = dylib_path
+            let file_name = binary_path
                 .file_name()
                 .and_then(|f| f.to_str())
                 .ok_or(Error::TweakInvalidPath)?;
             format!("@rpath/{}", file_name)
         };
 
-        let mut macho = MachO::new(&executable_path)?;
-        macho.add_dylib(&inject_path)?;
-        macho.write_changes()?;
+        Ok(install_name)
+    }
+
+    /// Rewrites the `LC_ID_DYLIB` of a copied binary, debs usually ship absolute
+    /// install names (e.g. `/Library/Frameworks/...`) that won't load inside the app.
+    fn normalize_install_name(binary_path: &Path, install_name: &str) -> Result<(), Error> {
+        let mut macho = MachO::new(binary_path)?;
+        macho.set_install_name(install_name)?;
 
         Ok(())
     }
@@ -0,0 +1,383 @@
+//! Checks a signed bundle the way installd does, so a broken signature shows up
+//! before the device rejects the upload.
+//!
+//! For every signed component this recomputes the code directory hashes of its
+//! executable, checks that `_CodeSignature/CodeResources` seals every file and
+//! nested bundle exactly as they are now, and that the signed entitlements are
+//! ones the embedded provisioning profile grants.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use plist::{Dictionary, Value};
+use plume_core::{MachFile, MachOExt, MobileProvision, SignatureMismatch};
+use sha2::{Digest, Sha256};
+
+use crate::{Bundle, BundleType, Error, entitlements::entitlement_allowed};
+
+const CODE_RESOURCES_PATH: &str = "_CodeSignature/CodeResources";
+
+/// CDHashes of every slice of the executables checked so far, so a parent can
+/// compare its seals without parsing nested code again.
+type CdHashes = HashMap<PathBuf, Vec<String>>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyIssue {
+    /// The executable has no code signature.
+    Unsigned(String),
+    /// The executable doesn't match its code directory.
+    Signature {
+        executable: String,
+        mismatch: SignatureMismatch,
+    },
+    /// `_CodeSignature/CodeResources` is missing or unreadable.
+    MissingCodeResources,
+    /// A file that isn't listed in `CodeResources`.
+    UnsealedFile(String),
+    /// A file whose contents don't match its `CodeResources` hash.
+    ModifiedFile(String),
+    /// `CodeResources` lists a file that isn't in the bundle.
+    MissingFile(String),
+    /// A nested bundle or dylib that isn't sealed, or was re-signed after its parent.
+    UnsealedNestedCode(String),
+    /// A signed entitlement the embedded provisioning profile doesn't grant.
+    EntitlementNotAllowed(String),
+}
+
+impl fmt::Display for VerifyIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyIssue::Unsigned(executable) => write!(f, "executable {executable} is unsigned"),
+            VerifyIssue::Signature {
+                executable,
+                mismatch,
+            } => write!(f, "executable {executable}: {mismatch}"),
+            VerifyIssue::MissingCodeResources => write!(f, "{CODE_RESOURCES_PATH} is missing"),
+            VerifyIssue::UnsealedFile(path) => write!(f, "{path} is not sealed"),
+            VerifyIssue::ModifiedFile(path) => write!(f, "{path} was modified after signing"),
+            VerifyIssue::MissingFile(path) => write!(f, "{path} is sealed but missing"),
+            VerifyIssue::UnsealedNestedCode(path) => {
+                write!(f, "nested code {path} is not sealed by its signature")
+            }
+            VerifyIssue::EntitlementNotAllowed(key) => {
+                write!(
+                    f,
+                    "entitlement {key} is not allowed by the provisioning profile"
+                )
+            }
+        }
+    }
+}
+
+/// A [`VerifyIssue`] and the bundle it was found in.
+#[derive(Debug, Clone)]
+pub struct VerifyMessage {
+    /// Bundle path relative to the package root, e.g. `Foo.app/PlugIns/Widget.appex`.
+    pub bundle: PathBuf,
+    pub issue: VerifyIssue,
+}
+
+impl fmt::Display for VerifyMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.bundle.display(), self.issue)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    pub messages: Vec<VerifyMessage>,
+}
+
+impl VerifyReport {
+    pub fn is_valid(&self) -> bool {
+        self.messages.is_empty()
+    }
+}
+
+impl Bundle {
+    /// Checks the signature of the bundle and everything nested in it.
+    pub fn verify(&self) -> Result<VerifyReport, Error> {
+        let root = self.bundle_dir().parent().unwrap_or(self.bundle_dir());
+
+        // innermost first so nested code is only parsed once
+        let mut cdhashes = CdHashes::new();
+        let mut results = Vec::new();
+
+        for bundle in &self.collect_bundles_sorted()? {
+            if *bundle.bundle_type() == BundleType::Unknown {
+                continue;
+            }
+
+            let relative = bundle
+                .bundle_dir()
+                .strip_prefix(root)
+                .unwrap_or(bundle.bundle_dir())
+                .to_path_buf();
+
+            results.push((relative, verify_bundle(bundle, &mut cdhashes)?));
+        }
+
+        // outermost first, the order the device checks them in
+        let mut report = VerifyReport::default();
+        for (relative, issues) in results.into_iter().rev() {
+            for issue in issues {
+                report.messages.push(VerifyMessage {
+                    bundle: relative.clone(),
+                    issue,
+                });
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Errors with [`Error::BundleSignatureInvalid`] listing every problem
+    /// [`Bundle::verify`] finds, the device would refuse to install it.
+    pub fn ensure_verified(&self) -> Result<(), Error> {
+        let report = self.verify()?;
+        if report.is_valid() {
+            return Ok(());
+        }
+
+        Err(Error::BundleSignatureInvalid(
+            report.messages.iter().map(ToString::to_string).collect(),
+        ))
+    }
+}
+
+fn verify_bundle(bundle: &Bundle, cdhashes: &mut CdHashes) -> Result<Vec<VerifyIssue>, Error> {
+    let mut issues = Vec::new();
+
+    let Some(executable_path) = bundle.executable_path() else {
+        // resource-only frameworks, lint reports the others
+        return Ok(issues);
+    };
+    let executable = executable_path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let is_dylib = *bundle.bundle_type() == BundleType::Dylib;
+    let (info_plist, code_resources) = if is_dylib {
+        (None, None)
+    } else {
+        (
+            fs::read(bundle.bundle_dir().join("Info.plist")).ok(),
+            fs::read(bundle.bundle_dir().join(CODE_RESOURCES_PATH)).ok(),
+        )
+    };
+
+    // read-only, so parse a buffer that's dropped with this bundle
+    let data = fs::read(&executable_path)?;
+    let macho = MachFile::parse(&data)?;
+    for slice in macho.iter_macho() {
+        let issues_for_slice =
+            match slice.verify_signature(info_plist.as_deref(), code_resources.as_deref())? {
+                None => vec![VerifyIssue::Unsigned(executable.clone())],
+                Some(mismatches) => mismatches
+                    .into_iter()
+                    .map(|mismatch| VerifyIssue::Signature {
+                        executable: executable.clone(),
+                        mismatch,
+                    })
+                    .collect(),
+            };
+
+        for issue in issues_for_slice {
+            if !issues.contains(&issue) {
+                issues.push(issue);
+            }
+        }
+    }
+
+    cdhashes.insert(executable_path.clone(), slice_cdhashes(&macho)?);
+
+    if is_dylib {
+        return Ok(issues);
+    }
+
+    match code_resources.and_then(|data| Value::from_reader(std::io::Cursor::new(data)).ok()) {
+        Some(resources) => {
+            let files = resources
+                .as_dictionary()
+                .and_then(|d| d.get("files2"))
+                .and_then(Value::as_dictionary)
+                .cloned()
+                .unwrap_or_default();
+            verify_resources(bundle, &executable, &files, cdhashes, &mut issues)?;
+        }
+        None => issues.push(VerifyIssue::MissingCodeResources),
+    }
+
+    let profile_path = bundle.bundle_dir().join("embedded.mobileprovision");
+    if profile_path.is_file() {
+        let profile = MobileProvision::load_with_path(&profile_path)?;
+        if let Some(entitlements) = macho.nth_macho(0)?.embedded_entitlements()? {
+            for (key, value) in &entitlements {
+                let allowed = profile
+                    .entitlements()
+                    .get(key)
+                    .is_some_and(|allowed| entitlement_allowed(value, allowed));
+
+                if !allowed {
+                    issues.push(VerifyIssue::EntitlementNotAllowed(key.clone()));
+                }
+            }
+        }
+    }
+
+    Ok(issues)
+}
+
+/// Compares the bundle's files against the `files2` section of its `CodeResources`.
+fn verify_resources(
+    bundle: &Bundle,
+    executable: &str,
+    sealed: &Dictionary,
+    cdhashes: &mut CdHashes,
+    issues: &mut Vec<VerifyIssue>,
+) -> Result<(), Error> {
+    let mut found = Vec::new();
+    collect_files(bundle.bundle_dir(), "", &mut found)?;
+
+    let mut seen = HashSet::new();
+    for (relative, file) in found {
+        if is_omitted(&relative) || relative == executable {
+            continue;
+        }
+        seen.insert(relative.clone());
+
+        let Some(entry) = sealed.get(&relative).and_then(Value::as_dictionary) else {
+            issues.push(match file {
+                FileKind::NestedCode(_) => VerifyIssue::UnsealedNestedCode(relative),
+                _ => VerifyIssue::UnsealedFile(relative),
+            });
+            continue;
+        };
+
+        let matches = match &file {
+            FileKind::Symlink(target) => {
+                entry.get("symlink").and_then(Value::as_string) == Some(target.as_str())
+            }
+            FileKind::File(path) => match entry.get("hash2").and_then(Value::as_data) {
+                Some(hash) => Sha256::digest(fs::read(path)?).as_slice() == hash,
+                // Mach-O files can be sealed by their signature instead
+                None => cdhash_matches(path, entry, cdhashes),
+            },
+            FileKind::NestedCode(executable_path) => executable_path
+                .as_deref()
+                .is_some_and(|path| cdhash_matches(path, entry, cdhashes)),
+        };
+
+        if !matches {
+            issues.push(match file {
+                FileKind::NestedCode(_) => VerifyIssue::UnsealedNestedCode(relative),
+                _ => VerifyIssue::ModifiedFile(relative),
+            });
+        }
+    }
+
+    for (relative, entry) in sealed {
+        let optional = entry
+            .as_dictionary()
+            .and_then(|e| e.get("optional"))
+            .and_then(Value::as_boolean)
+            .unwrap_or(false);
+
+        if !optional && !seen.contains(relative) && !is_omitted(relative) {
+            issues.push(VerifyIssue::MissingFile(relative.clone()));
+        }
+    }
+
+    Ok(())
+}
+
+enum FileKind {
+    File(PathBuf),
+    Symlink(String),
+    /// A nested signed bundle, with its executable.
+    NestedCode(Option<PathBuf>),
+}
+
+/// Every file under `dir` keyed by its `/` separated path, stopping at nested
+/// signed bundles, which are sealed as a whole.
+fn collect_files(
+    dir: &Path,
+    prefix: &str,
+    found: &mut Vec<(String, FileKind)>,
+) -> Result<(), Error> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let relative = format!("{prefix}{name}");
+        let file_type = entry.file_type()?;
+
+        if file_type.is_symlink() {
+            let target = fs::read_link(&path)?.to_string_lossy().into_owned();
+            found.push((relative, FileKind::Symlink(target)));
+        } else if file_type.is_dir() {
+            if prefix.is_empty() && name == "_CodeSignature" {
+                continue;
+            }
+
+            match Bundle::new(&path) {
+                Ok(nested) if nested.bundle_type().should_be_signed() => {
+                    found.push((relative, FileKind::NestedCode(nested.executable_path())));
+                }
+                _ => collect_files(&path, &format!("{relative}/"), found)?,
+            }
+        } else {
+            found.push((relative, FileKind::File(path)));
+        }
+    }
+
+    Ok(())
+}
+
+/// Files the default resource rules leave out of `CodeResources`.
+fn is_omitted(relative: &str) -> bool {
+    let name = relative.rsplit('/').next().unwrap_or(relative);
+    matches!(relative, "Info.plist" | "PkgInfo")
+        || name == ".DS_Store"
+        || (name == "locversion.plist" && relative.contains(".lproj/"))
+}
+
+/// Whether `entry` seals the Mach-O at `path` by CDHash, parsing it only if no
+/// bundle checked before has.
+fn cdhash_matches(path: &Path, entry: &Dictionary, cdhashes: &mut CdHashes) -> bool {
+    let Some(expected) = entry.get("cdhash").and_then(Value::as_data) else {
+        return false;
+    };
+    let expected: String = expected.iter().map(|b| format!("{b:02x}")).collect();
+
+    if !cdhashes.contains_key(path) {
+        let Ok(data) = fs::read(path) else {
+            return false;
+        };
+        let Ok(found) = MachFile::parse(&data)
+            .map_err(Error::from)
+            .and_then(|macho| slice_cdhashes(&macho))
+        else {
+            return false;
+        };
+        cdhashes.insert(path.to_path_buf(), found);
+    }
+
+    cdhashes[path].contains(&expected)
+}
+
+/// The CDHash of every code directory in every slice.
+fn slice_cdhashes(macho: &MachFile<'_>) -> Result<Vec<String>, Error> {
+    let mut found = Vec::new();
+    for slice in macho.iter_macho() {
+        if let Some(info) = slice.signature_info()? {
+            found.extend(info.cdhashes.into_iter().map(|(_, cdhash)| cdhash));
+        }
+    }
+
+    Ok(found)
+}